@@ -31,7 +31,13 @@ export default function Home() {
             throw new Error("Invalid response format");
           }
 
-          const nodes = processTransactionNodes(res.transactions);
+          const nodes = [
+            ...processTransactionNodes(res.transactions),
+            ...processPseudoTransactionNodes(
+              res.pseudo_transactions,
+              res.transactions.length,
+            ),
+          ];
           const edges = processTransactionEdges(res.dags);
           setGraphData({ nodes, edges });
         })
@@ -64,6 +70,21 @@ export default function Home() {
       });
     }
 
+    // system calls (index -1) above the transactions, withdrawals below
+    function processPseudoTransactionNodes(pseudoTransactions, txCount) {
+      const r = 5;
+      const offset = (Math.ceil(txCount / 4) + 1) * r;
+      return (pseudoTransactions || []).map((item) => ({
+        ...item,
+        id: item.index,
+        label: item.name,
+        size: 10,
+        color: "#999",
+        x: 0,
+        y: item.index < 0 ? offset : -offset,
+      }));
+    }
+
     function processTransactionEdges(dags) {
       return (dags || []).map((item, i) => ({
         ...item,
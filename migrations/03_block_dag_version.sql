@@ -0,0 +1,7 @@
+-- Blocks analyzed before storage dependencies were keyed by (address, slot)
+-- are flagged as version 1. New rows start at 0 until the analyzer has
+-- written their DAG.
+ALTER TABLE blocks ADD COLUMN IF NOT EXISTS dag_version SMALLINT NOT NULL DEFAULT 1;
+ALTER TABLE blocks ALTER COLUMN dag_version SET DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS blocks_dag_version_idx ON blocks (dag_version);
@@ -0,0 +1,14 @@
+-- critical_path_length is also the number of topological levels
+CREATE TABLE IF NOT EXISTS block_parallelism_stats (
+    block_number BIGINT NOT NULL PRIMARY KEY,
+    tx_count BIGINT NOT NULL,
+    -- pre-block system calls and withdrawals, not counted in tx_count
+    pseudo_tx_count BIGINT NOT NULL,
+    edge_count BIGINT NOT NULL,
+    critical_path_length BIGINT NOT NULL,
+    max_width BIGINT NOT NULL,
+    components BIGINT NOT NULL,
+    speedup DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,5 @@
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS gas_used BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS total_gas BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS critical_path_gas BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS gas_speedup DOUBLE PRECISION NOT NULL DEFAULT 1;
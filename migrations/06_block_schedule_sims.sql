@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS block_schedule_sims (
+    block_number BIGINT NOT NULL,
+    workers BIGINT NOT NULL,
+    makespan_gas BIGINT NOT NULL,
+    utilization DOUBLE PRECISION NOT NULL,
+    speedup DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (block_number, workers)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE block_schedule_sims ADD COLUMN IF NOT EXISTS stm_makespan_gas BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_schedule_sims ADD COLUMN IF NOT EXISTS stm_aborts BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_schedule_sims ADD COLUMN IF NOT EXISTS stm_wasted_gas BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_schedule_sims ADD COLUMN IF NOT EXISTS stm_speedup DOUBLE PRECISION NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- conflicting keys behind each edge: [{resource, hazard, address, slot}]
+ALTER TABLE transaction_dags ADD COLUMN IF NOT EXISTS reasons JSONB;
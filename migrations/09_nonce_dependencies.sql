@@ -0,0 +1,7 @@
+-- edges by the one resource that caused them, nonce before storage before
+-- code before balance, so nonce (same sender) edges can be told apart from
+-- balance contention
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS balance_edge_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS code_edge_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS storage_edge_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS nonce_edge_count BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+-- balances excluded from dependency detection, and the metrics of the DAG
+-- built without any exclusion
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS balance_exclusions JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS naive_edge_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS naive_critical_path_length BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS naive_speedup DOUBLE PRECISION NOT NULL DEFAULT 1;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS naive_critical_path_gas BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE block_parallelism_stats ADD COLUMN IF NOT EXISTS naive_gas_speedup DOUBLE PRECISION NOT NULL DEFAULT 1;
+
+-- existing rows were computed without exclusions
+UPDATE block_parallelism_stats SET
+    naive_edge_count = edge_count,
+    naive_critical_path_length = critical_path_length,
+    naive_speedup = speedup,
+    naive_critical_path_gas = critical_path_gas,
+    naive_gas_speedup = gas_speedup;
@@ -0,0 +1,2 @@
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS source_hash TEXT;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS mint TEXT;
@@ -0,0 +1 @@
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS authorization_count BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS reorgs (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    -- last block shared by the orphaned and the canonical branch
+    fork_block BIGINT NOT NULL,
+    -- stored blocks deleted above fork_block
+    orphaned_blocks BIGINT NOT NULL,
+    old_block_hash TEXT NOT NULL,
+    new_block_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS reorgs_chain_fork_idx ON reorgs (chain_id, fork_block);
@@ -0,0 +1,2 @@
+-- head the analyzer follows: latest, safe, finalized or latest-N
+ALTER TABLE parallel_analyzer_state ADD COLUMN IF NOT EXISTS horizon TEXT NOT NULL DEFAULT 'latest';
@@ -0,0 +1,19 @@
+-- historical block ranges claimed by backfill workers
+CREATE TABLE IF NOT EXISTS analysis_ranges (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    start_block BIGINT NOT NULL,
+    end_block BIGINT NOT NULL,
+    -- "backward" from end_block or "forward" from start_block
+    direction TEXT NOT NULL,
+    -- next block to analyze, past the range once it is done
+    next_block BIGINT NOT NULL,
+    -- "pending", "running" or "done"
+    status TEXT NOT NULL DEFAULT 'pending',
+    worker TEXT,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (chain_id, start_block)
+);
+
+CREATE INDEX IF NOT EXISTS analysis_ranges_chain_status_idx ON analysis_ranges (chain_id, status);
@@ -0,0 +1,18 @@
+-- read/write sets of every transaction and pseudo-transaction, so blocks can
+-- be re-analyzed without the execution api
+CREATE TABLE IF NOT EXISTS transaction_state_sets (
+    block_number BIGINT NOT NULL,
+    tx_index BIGINT NOT NULL,
+    -- {"balance": [address], "code": [address], "nonce": [address],
+    --  "storage": {address: [slot]}}
+    read_set JSONB NOT NULL,
+    write_set JSONB NOT NULL,
+    -- EIP-7702 authorities
+    authorities JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (block_number, tx_index)
+);
+
+-- needed by lazy_fee.exclude_fee_recipient when re-analyzing from the database
+ALTER TABLE blocks ADD COLUMN IF NOT EXISTS fee_recipient TEXT;
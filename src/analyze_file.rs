@@ -0,0 +1,240 @@
+use std::{fs, path::Path};
+
+use alloy::{
+    network::{AnyRpcBlock as AlloyBlock, AnyTransactionReceipt as TransactionReceipt},
+    rpc::types::trace::geth::TraceResult,
+};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_analyzer::BlockAnalyzer,
+    block_stm::BlockStmSim,
+    config::{AnalysisConfig, OutputFormat},
+    dag_metrics::{DagMetrics, ScheduleSim},
+    db::transaction_dag::DagReason,
+    parallel_analyzer::{
+        BlockData, DAG_VERSION, block_traces_to_states, block_transactions, dep_type_hazards,
+        dep_type_resources,
+    },
+};
+
+/// A block with the prestate and diff-mode results of
+/// `debug_traceBlockByNumber`, as returned by the execution api.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordedBlock {
+    /// `eth_getBlockByNumber` with full transactions
+    pub block: AlloyBlock,
+    /// `eth_getBlockReceipts`, every transaction counts as 0 gas without them
+    #[serde(default)]
+    pub receipts: Vec<TransactionReceipt>,
+    pub prestate_traces: Vec<TraceResult>,
+    pub diff_traces: Vec<TraceResult>,
+}
+
+/// A file holds one recorded block or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecordedBlocks {
+    One(Box<RecordedBlock>),
+    Many(Vec<RecordedBlock>),
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileEdge {
+    pub source: i64,
+    pub target: i64,
+    pub dep_type: i16,
+    pub resources: Vec<&'static str>,
+    pub hazards: Vec<&'static str>,
+    pub reasons: Vec<DagReason>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileAnalysis {
+    pub block_number: i64,
+    pub block_hash: String,
+    pub dag_version: i16,
+    pub balance_exclusions: Vec<String>,
+    pub metrics: DagMetrics,
+    /// metrics without `balance_exclusions`
+    pub naive_metrics: DagMetrics,
+    pub edges: Vec<FileEdge>,
+    pub schedules: Vec<ScheduleSim>,
+    pub stm_sims: Vec<BlockStmSim>,
+}
+
+/// Builds the DAG of a recorded block under the dependency rules of `analyzer`.
+pub fn analyze_recorded_block(
+    analyzer: &BlockAnalyzer,
+    recorded: RecordedBlock,
+) -> Result<FileAnalysis> {
+    let block_number = recorded.block.header.number as i64;
+    let transactions = block_transactions(&recorded.block)?;
+    let tx_states = block_traces_to_states(
+        block_number,
+        &transactions,
+        recorded.prestate_traces,
+        recorded.diff_traces,
+    )?;
+    let data = BlockData {
+        block: recorded.block,
+        receipts: recorded.receipts,
+        tx_states,
+    };
+    let (rules, analysis) = analyzer.block_analysis(&data, &data.gas_used()?)?;
+    let mut balance_exclusions: Vec<String> = rules
+        .balance_exclusions
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+    balance_exclusions.sort();
+    Ok(FileAnalysis {
+        block_number,
+        block_hash: data.block.header.hash.to_string(),
+        dag_version: DAG_VERSION,
+        balance_exclusions,
+        metrics: analysis.metrics,
+        naive_metrics: analysis.naive_metrics,
+        edges: analysis
+            .edges
+            .iter()
+            .map(|edge| FileEdge {
+                source: edge.source,
+                target: edge.target,
+                dep_type: edge.dep_type,
+                resources: dep_type_resources(edge.dep_type),
+                hazards: dep_type_hazards(edge.dep_type),
+                reasons: edge.conflicts.iter().map(DagReason::from).collect(),
+            })
+            .collect(),
+        schedules: analysis.schedules,
+        stm_sims: analysis.stm_sims,
+    })
+}
+
+fn print_table(analysis: &FileAnalysis) {
+    let metrics = &analysis.metrics;
+    println!("block {} ({})", analysis.block_number, analysis.block_hash);
+    println!(
+        "  txs {}, edges {}, critical path {}, max width {}, components {}",
+        metrics.tx_count,
+        metrics.edge_count,
+        metrics.critical_path_length,
+        metrics.max_width,
+        metrics.components
+    );
+    println!(
+        "  speedup {:.2}, gas speedup {:.2}, naive edges {}, naive gas speedup {:.2}",
+        metrics.speedup,
+        metrics.gas_speedup,
+        analysis.naive_metrics.edge_count,
+        analysis.naive_metrics.gas_speedup
+    );
+    println!();
+    println!(
+        "  {:>8} {:>8} {:>8}  {:<24} hazards",
+        "source", "target", "dep_type", "resources"
+    );
+    for edge in &analysis.edges {
+        println!(
+            "  {:>8} {:>8} {:>#8x}  {:<24} {}",
+            edge.source,
+            edge.target,
+            edge.dep_type,
+            edge.resources.join(","),
+            edge.hazards.join(",")
+        );
+    }
+    println!();
+    println!(
+        "  {:>8} {:>14} {:>11} {:>8} {:>10} {:>11}",
+        "workers", "makespan_gas", "utilization", "speedup", "stm_aborts", "stm_speedup"
+    );
+    for (sim, stm) in analysis.schedules.iter().zip(&analysis.stm_sims) {
+        println!(
+            "  {:>8} {:>14} {:>11.2} {:>8.2} {:>10} {:>11.2}",
+            sim.workers, sim.makespan_gas, sim.utilization, sim.speedup, stm.aborts, stm.speedup
+        );
+    }
+    println!();
+}
+
+/// Analyses the recorded blocks of `path` without the database or the
+/// execution api and prints the result.
+pub fn analyze_file(config: AnalysisConfig, path: &Path, format: OutputFormat) -> Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let recorded = match serde_json::from_str(&content)
+        .with_context(|| format!("could not parse {}", path.display()))?
+    {
+        RecordedBlocks::One(block) => vec![*block],
+        RecordedBlocks::Many(blocks) => blocks,
+    };
+    let analyzer = BlockAnalyzer::new(config);
+    let analyses = recorded
+        .into_iter()
+        .map(|block| analyze_recorded_block(&analyzer, block))
+        .collect::<Result<Vec<_>>>()?;
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&analyses)?),
+        OutputFormat::Table => analyses.iter().for_each(print_table),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, address};
+
+    use super::*;
+    use crate::parallel_analyzer::{DEP_STORAGE, HAZARD_RAW};
+
+    fn recorded_block() -> RecordedBlock {
+        serde_json::from_str(include_str!("../testdata/recorded_block.json")).unwrap()
+    }
+
+    #[test]
+    fn analyzes_recorded_block() {
+        let analyzer = BlockAnalyzer::new(AnalysisConfig::default());
+        let analysis = analyze_recorded_block(&analyzer, recorded_block()).unwrap();
+        assert_eq!(analysis.block_number, 16);
+        assert_eq!(analysis.metrics.tx_count, 2);
+        assert_eq!(analysis.metrics.critical_path_length, 2);
+        assert!(analysis.balance_exclusions.is_empty());
+        assert_eq!(analysis.edges.len(), 1);
+        let edge = &analysis.edges[0];
+        assert_eq!((edge.source, edge.target), (1, 0));
+        assert_eq!(edge.dep_type, DEP_STORAGE * HAZARD_RAW);
+        assert_eq!(edge.reasons.len(), 1);
+        let reason = &edge.reasons[0];
+        assert_eq!(
+            (reason.resource.as_str(), reason.hazard.as_str()),
+            ("storage", "raw")
+        );
+        assert_eq!(
+            reason.address.parse::<Address>().unwrap(),
+            address!("0x00000000000000000000000000000000000000cc")
+        );
+        assert_eq!(
+            reason.slot.as_deref(),
+            Some("0x0000000000000000000000000000000000000000000000000000000000000001")
+        );
+        assert_eq!(
+            analysis.schedules.len(),
+            analyzer.config.simulated_workers.len()
+        );
+    }
+
+    #[test]
+    fn applies_lazy_fee_exclusions() {
+        let mut config = AnalysisConfig::default();
+        config.lazy_fee.exclude_fee_recipient = true;
+        let analysis =
+            analyze_recorded_block(&BlockAnalyzer::new(config), recorded_block()).unwrap();
+        assert_eq!(
+            analysis.balance_exclusions,
+            vec![address!("0x0000000000000000000000000000000000000fee").to_string()]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+
+use alloy::{
+    primitives::TxHash,
+    transports::{RpcError, TransportError},
+};
+
+/// Unexpected node responses and missing state that stop a block from being
+/// analyzed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalyzerError {
+    /// the execution api does not have the block yet
+    BlockNotFound(i64),
+    /// the execution api does not have the block receipts yet
+    ReceiptsNotFound(i64),
+    /// the block came back with transaction hashes only
+    TransactionsNotFull(i64),
+    /// a transaction or receipt of a mined block without its index
+    MissingTransactionIndex(TxHash),
+    /// a transaction of a mined block without its block number
+    MissingBlockNumber(TxHash),
+    /// a trace of another tracer or mode than requested
+    UnexpectedTraceFrame {
+        tx_hash: TxHash,
+        expected: &'static str,
+    },
+    /// a block trace entry for another transaction
+    TraceMismatch { expected: TxHash, traced: TxHash },
+    /// the node failed to trace one transaction of a block trace
+    TraceFailed { tx_hash: TxHash, error: String },
+    /// a block trace with a different number of entries than transactions
+    TraceCountMismatch {
+        block_number: i64,
+        transactions: usize,
+        traces: usize,
+    },
+    /// no `parallel_analyzer_state` row for the chain
+    AnalyzerStateNotFound(i64),
+    /// a block stored without `transaction_state_sets` rows
+    StateSetsNotStored(i64),
+    /// the stored chain diverges from the canonical one more than
+    /// `max_reorg_depth` blocks below `block_number`
+    ReorgTooDeep { block_number: i64, max_depth: i64 },
+}
+
+impl AnalyzerError {
+    /// Every variant's `kind`, in declaration order.
+    pub const KINDS: [&'static str; 12] = [
+        "block_not_found",
+        "receipts_not_found",
+        "transactions_not_full",
+        "missing_transaction_index",
+        "missing_block_number",
+        "unexpected_trace_frame",
+        "trace_mismatch",
+        "trace_failed",
+        "trace_count_mismatch",
+        "analyzer_state_not_found",
+        "state_sets_not_stored",
+        "reorg_too_deep",
+    ];
+
+    /// Stable name of the variant, used in logs and metrics.
+    pub fn kind(&self) -> &'static str {
+        Self::KINDS[self.kind_index()]
+    }
+
+    pub(crate) fn kind_index(&self) -> usize {
+        match self {
+            AnalyzerError::BlockNotFound(_) => 0,
+            AnalyzerError::ReceiptsNotFound(_) => 1,
+            AnalyzerError::TransactionsNotFull(_) => 2,
+            AnalyzerError::MissingTransactionIndex(_) => 3,
+            AnalyzerError::MissingBlockNumber(_) => 4,
+            AnalyzerError::UnexpectedTraceFrame { .. } => 5,
+            AnalyzerError::TraceMismatch { .. } => 6,
+            AnalyzerError::TraceFailed { .. } => 7,
+            AnalyzerError::TraceCountMismatch { .. } => 8,
+            AnalyzerError::AnalyzerStateNotFound(_) => 9,
+            AnalyzerError::StateSetsNotStored(_) => 10,
+            AnalyzerError::ReorgTooDeep { .. } => 11,
+        }
+    }
+
+    /// A missing state row or a reorg too deep to roll back is not the
+    /// block's fault: skipping the block would lose it or leave the orphaned
+    /// branch stored, so the head follower stops instead.
+    pub fn stops_follower(&self) -> bool {
+        matches!(
+            self,
+            AnalyzerError::AnalyzerStateNotFound(_) | AnalyzerError::ReorgTooDeep { .. }
+        )
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            AnalyzerError::BlockNotFound(_) | AnalyzerError::ReceiptsNotFound(_) => {
+                ErrorClass::MissingBlock
+            }
+            // a node still indexing or load balanced across nodes may answer
+            // differently next time
+            AnalyzerError::TransactionsNotFull(_)
+            | AnalyzerError::MissingTransactionIndex(_)
+            | AnalyzerError::MissingBlockNumber(_)
+            | AnalyzerError::TraceMismatch { .. }
+            | AnalyzerError::TraceFailed { .. }
+            | AnalyzerError::TraceCountMismatch { .. } => ErrorClass::TransientRpc,
+            AnalyzerError::UnexpectedTraceFrame { .. } => ErrorClass::TracerUnsupported,
+            AnalyzerError::AnalyzerStateNotFound(_)
+            | AnalyzerError::StateSetsNotStored(_)
+            | AnalyzerError::ReorgTooDeep { .. } => ErrorClass::Other,
+        }
+    }
+}
+
+impl fmt::Display for AnalyzerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzerError::BlockNotFound(block_number) => {
+                write!(f, "block {block_number} not found")
+            }
+            AnalyzerError::ReceiptsNotFound(block_number) => {
+                write!(f, "receipts of block {block_number} not found")
+            }
+            AnalyzerError::TransactionsNotFull(block_number) => {
+                write!(f, "block {block_number} returned without full transactions")
+            }
+            AnalyzerError::MissingTransactionIndex(tx_hash) => {
+                write!(f, "transaction {tx_hash} has no transaction index")
+            }
+            AnalyzerError::MissingBlockNumber(tx_hash) => {
+                write!(f, "transaction {tx_hash} has no block number")
+            }
+            AnalyzerError::UnexpectedTraceFrame { tx_hash, expected } => {
+                write!(f, "trace of {tx_hash} is not a {expected} frame")
+            }
+            AnalyzerError::TraceMismatch { expected, traced } => {
+                write!(f, "trace for {traced} returned in place of {expected}")
+            }
+            AnalyzerError::TraceFailed { tx_hash, error } => {
+                write!(f, "trace of {tx_hash} failed: {error}")
+            }
+            AnalyzerError::TraceCountMismatch {
+                block_number,
+                transactions,
+                traces,
+            } => write!(
+                f,
+                "block {block_number} has {transactions} transactions but {traces} traces"
+            ),
+            AnalyzerError::AnalyzerStateNotFound(chain_id) => {
+                write!(f, "parallel analyzer state of chain {chain_id} not found")
+            }
+            AnalyzerError::StateSetsNotStored(block_number) => {
+                write!(f, "read/write sets of block {block_number} not stored")
+            }
+            AnalyzerError::ReorgTooDeep {
+                block_number,
+                max_depth,
+            } => write!(
+                f,
+                "reorg below block {block_number} is deeper than max_reorg_depth {max_depth}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AnalyzerError {}
+
+/// What went wrong while analyzing a block, deciding whether it is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// connection, timeout, rate limit or other node-side hiccup
+    TransientRpc,
+    /// the node does not have the block or its receipts yet
+    MissingBlock,
+    /// the node rejects the prestate tracer
+    TracerUnsupported,
+    Database,
+    Other,
+}
+
+impl ErrorClass {
+    pub const ALL: [ErrorClass; 5] = [
+        ErrorClass::TransientRpc,
+        ErrorClass::MissingBlock,
+        ErrorClass::TracerUnsupported,
+        ErrorClass::Database,
+        ErrorClass::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::TransientRpc => "transient_rpc",
+            ErrorClass::MissingBlock => "missing_block",
+            ErrorClass::TracerUnsupported => "tracer_unsupported",
+            ErrorClass::Database => "database",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    /// Whether retrying the same block later can succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorClass::TransientRpc | ErrorClass::MissingBlock | ErrorClass::Database
+        )
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// JSON-RPC "method not found"
+const METHOD_NOT_FOUND: i64 = -32601;
+
+fn classify_rpc_error(error: &TransportError) -> ErrorClass {
+    match error {
+        RpcError::ErrorResp(payload) => {
+            let message = payload.message.to_lowercase();
+            if payload.code == METHOD_NOT_FOUND
+                || message.contains("tracer")
+                || message.contains("not supported")
+            {
+                ErrorClass::TracerUnsupported
+            } else if message.contains("not found") {
+                ErrorClass::MissingBlock
+            } else {
+                ErrorClass::TransientRpc
+            }
+        }
+        RpcError::NullResp | RpcError::Transport(_) => ErrorClass::TransientRpc,
+        _ => ErrorClass::Other,
+    }
+}
+
+/// Classifies an analyzer error by the first known error in its chain.
+pub fn classify_error(error: &eyre::Report) -> ErrorClass {
+    error
+        .chain()
+        .find_map(|cause| {
+            if let Some(error) = cause.downcast_ref::<TransportError>() {
+                Some(classify_rpc_error(error))
+            } else if let Some(error) = cause.downcast_ref::<AnalyzerError>() {
+                Some(error.class())
+            } else if cause.is::<sqlx::Error>() {
+                Some(ErrorClass::Database)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(ErrorClass::Other)
+}
+
+/// The `AnalyzerError` in an error chain, if there is one.
+pub fn analyzer_error(error: &eyre::Report) -> Option<&AnalyzerError> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<AnalyzerError>())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{rpc::json_rpc::ErrorPayload, transports::TransportErrorKind};
+    use eyre::{WrapErr, eyre};
+
+    use super::*;
+
+    fn error_resp(code: i64, message: &'static str) -> TransportError {
+        RpcError::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: None,
+        })
+    }
+
+    fn classify(error: impl std::error::Error + Send + Sync + 'static) -> ErrorClass {
+        classify_error(
+            &Err::<(), _>(error)
+                .wrap_err("analyzing block 16")
+                .unwrap_err(),
+        )
+    }
+
+    #[test]
+    fn classifies_rpc_errors() {
+        assert_eq!(
+            classify(TransportErrorKind::custom_str("connection reset")),
+            ErrorClass::TransientRpc
+        );
+        let null_resp: TransportError = RpcError::NullResp;
+        assert_eq!(classify(null_resp), ErrorClass::TransientRpc);
+        assert_eq!(
+            classify(error_resp(-32005, "rate limit exceeded")),
+            ErrorClass::TransientRpc
+        );
+        assert_eq!(
+            classify(error_resp(-32000, "block not found")),
+            ErrorClass::MissingBlock
+        );
+        assert_eq!(
+            classify(error_resp(METHOD_NOT_FOUND, "the method does not exist")),
+            ErrorClass::TracerUnsupported
+        );
+        assert_eq!(
+            classify(error_resp(-32000, "tracer not found")),
+            ErrorClass::TracerUnsupported
+        );
+        let unsupported: TransportError = RpcError::UnsupportedFeature("subscriptions");
+        assert_eq!(classify(unsupported), ErrorClass::Other);
+    }
+
+    #[test]
+    fn classifies_analyzer_database_and_other_errors() {
+        assert_eq!(
+            classify(AnalyzerError::BlockNotFound(16)),
+            ErrorClass::MissingBlock
+        );
+        assert_eq!(
+            classify(AnalyzerError::TraceCountMismatch {
+                block_number: 16,
+                transactions: 2,
+                traces: 1,
+            }),
+            ErrorClass::TransientRpc
+        );
+        assert_eq!(
+            classify(AnalyzerError::UnexpectedTraceFrame {
+                tx_hash: TxHash::ZERO,
+                expected: "prestate",
+            }),
+            ErrorClass::TracerUnsupported
+        );
+        assert_eq!(
+            classify(AnalyzerError::StateSetsNotStored(16)),
+            ErrorClass::Other
+        );
+        assert_eq!(classify(sqlx::Error::PoolTimedOut), ErrorClass::Database);
+        assert_eq!(classify_error(&eyre!("unexpected")), ErrorClass::Other);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashSet};
+
+use alloy::{
+    consensus::Typed2718,
+    network::AnyRpcTransaction as AlloyTransaction,
+    primitives::{Address, B256, U256},
+};
+
+use crate::{
+    analyzer_error::AnalyzerError,
+    chain_profile::{BEACON_ROOTS, ChainProfile, HISTORY_STORAGE, chain_profile},
+    config::AnalysisConfig,
+    parallel_analyzer::{
+        BlockAnalysis, BlockData, DependencyRules, TransactionStateSet, analyse_block_data,
+        block_transactions, tx_index,
+    },
+    pseudo_transactions::pseudo_transaction_states,
+};
+
+/// Dependency rules and DAG analysis of a block, without the database or the
+/// execution api.
+#[derive(Debug, Clone)]
+pub struct BlockAnalyzer {
+    pub config: AnalysisConfig,
+    pub chain_profile: ChainProfile,
+}
+
+impl BlockAnalyzer {
+    pub fn new(config: AnalysisConfig) -> Self {
+        Self {
+            chain_profile: chain_profile(config.chain_id, &config.chain_profiles),
+            config,
+        }
+    }
+
+    /// System addresses of the chain profile and balance exclusions
+    /// configured in `lazy_fee` for a block paid to `fee_recipient`.
+    pub fn dependency_rules(&self, fee_recipient: Option<Address>) -> DependencyRules {
+        let lazy_fee = &self.config.lazy_fee;
+        let mut balance_exclusions: HashSet<Address> =
+            lazy_fee.fee_vaults.iter().copied().collect();
+        if lazy_fee.exclude_fee_vaults {
+            balance_exclusions.extend(&self.chain_profile.fee_vaults);
+        }
+        if lazy_fee.exclude_fee_recipient {
+            balance_exclusions.extend(fee_recipient);
+        }
+        if lazy_fee.exclude_burn_address {
+            balance_exclusions.insert(Address::ZERO);
+        }
+        let mut ignored_addresses: HashSet<Address> = self
+            .chain_profile
+            .system_addresses
+            .iter()
+            .copied()
+            .collect();
+        if self.config.pseudo_transactions {
+            // modelled by the system calls pseudo-transaction
+            ignored_addresses.remove(&BEACON_ROOTS);
+            ignored_addresses.remove(&HISTORY_STORAGE);
+        }
+        DependencyRules {
+            ignored_addresses,
+            balance_exclusions,
+        }
+    }
+
+    /// Whether the transaction at `tx_index` is a system transaction of the
+    /// chain profile or, with `exclude_l1_attributes_tx`, the leading L1
+    /// attributes deposit.
+    pub fn is_system_tx(&self, tx_index: i64, tx_type: u8) -> bool {
+        self.chain_profile.system_tx_types.contains(&tx_type)
+            || (self.config.exclude_l1_attributes_tx
+                && tx_index == 0
+                && self.chain_profile.deposit_tx_type == Some(tx_type))
+    }
+
+    /// Whether `tx` is the deposit of the chain profile's `deposit_tx_type`.
+    pub fn is_deposit(&self, tx: &AlloyTransaction) -> bool {
+        self.chain_profile.deposit_tx_type == Some(tx.ty())
+    }
+
+    /// Source hash and minted value of a deposit transaction, each none when
+    /// the node left it out or `tx` is not a deposit.
+    pub fn deposit_fields(&self, tx: &AlloyTransaction) -> (Option<B256>, Option<U256>) {
+        if !self.is_deposit(tx) {
+            return (None, None);
+        }
+        // deposit fields land in the unknown envelope, or in the outer
+        // `other` fields depending on how the response was deserialized
+        let fields = tx
+            .inner
+            .inner
+            .as_unknown()
+            .map(|unknown| &unknown.inner.fields)
+            .into_iter()
+            .chain([&tx.other]);
+        let mut source_hash = None;
+        let mut mint = None;
+        for fields in fields {
+            source_hash = source_hash.or_else(|| fields.get_deserialized("sourceHash")?.ok());
+            mint = mint.or_else(|| fields.get_deserialized("mint")?.ok());
+        }
+        (source_hash, mint)
+    }
+
+    /// Read/write sets of the block's transactions, without the system
+    /// transactions of the chain profile and, with
+    /// `exclude_l1_attributes_tx`, without the leading L1 attributes deposit.
+    /// With `pseudo_transactions`, the pre-block system calls and the
+    /// withdrawals are added around them.
+    pub fn dag_tx_states(
+        &self,
+        data: &BlockData,
+    ) -> Result<BTreeMap<i64, TransactionStateSet>, AnalyzerError> {
+        let transactions = block_transactions(&data.block)?;
+        let mut system_txs = HashSet::new();
+        for tx in &transactions {
+            let tx_index = tx_index(tx)?;
+            if self.is_system_tx(tx_index, tx.ty()) {
+                system_txs.insert(tx_index);
+            }
+        }
+        Ok(self.select_dag_tx_states(
+            &data.tx_states,
+            &system_txs,
+            pseudo_transaction_states(&data.block, transactions.len()),
+        ))
+    }
+
+    /// `tx_states` without `system_txs`, with `pseudo_states` added when
+    /// `pseudo_transactions` is set.
+    pub(crate) fn select_dag_tx_states(
+        &self,
+        tx_states: &BTreeMap<i64, TransactionStateSet>,
+        system_txs: &HashSet<i64>,
+        pseudo_states: Vec<(i64, TransactionStateSet)>,
+    ) -> BTreeMap<i64, TransactionStateSet> {
+        let mut dag_tx_states: BTreeMap<i64, TransactionStateSet> = tx_states
+            .iter()
+            .filter(|(tx_index, _)| !system_txs.contains(tx_index))
+            .map(|(tx_index, state)| (*tx_index, state.clone()))
+            .collect();
+        if self.config.pseudo_transactions {
+            dag_tx_states.extend(pseudo_states);
+        }
+        dag_tx_states
+    }
+
+    /// Dependency rules of the block and the DAG, metrics and simulations
+    /// they produce.
+    pub fn block_analysis(
+        &self,
+        data: &BlockData,
+        gas_used: &BTreeMap<i64, u64>,
+    ) -> Result<(DependencyRules, BlockAnalysis), AnalyzerError> {
+        let rules = self.dependency_rules(Some(data.block.header.beneficiary));
+        let analysis = analyse_block_data(
+            &self.dag_tx_states(data)?,
+            gas_used,
+            block_transactions(&data.block)?.len(),
+            &rules,
+            &self.config.simulated_workers,
+        );
+        Ok((rules, analysis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, uint};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::parallel_analyzer::{StateSet, build_transaction_dag};
+
+    const SOURCE_HASH: B256 =
+        b256!("0x00000000000000000000000000000000000000000000000000000000000005ce");
+
+    /// OP mainnet, whose deposits have type 0x7e.
+    fn op_analyzer() -> BlockAnalyzer {
+        BlockAnalyzer::new(AnalysisConfig {
+            chain_id: 10,
+            ..Default::default()
+        })
+    }
+
+    fn transaction(tx: Value) -> AlloyTransaction {
+        let mut json = json!({
+            "hash": "0x0000000000000000000000000000000000000000000000000000000000000100",
+            "from": "0x00000000000000000000000000000000000000a0",
+            "to": "0x00000000000000000000000000000000000000cc",
+            "nonce": "0x0",
+            "gas": "0x5208",
+            "value": "0x0",
+            "input": "0x",
+            "blockHash": "0x000000000000000000000000000000000000000000000000000000000000b10c",
+            "blockNumber": "0x10",
+            "transactionIndex": "0x0",
+        });
+        json.as_object_mut()
+            .unwrap()
+            .extend(tx.as_object().unwrap().clone());
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn deposit(mint: Option<&str>) -> AlloyTransaction {
+        let mut tx = json!({
+            "type": "0x7e",
+            "sourceHash": SOURCE_HASH,
+            "isSystemTx": false,
+        });
+        if let Some(mint) = mint {
+            tx["mint"] = json!(mint);
+        }
+        transaction(tx)
+    }
+
+    #[test]
+    fn deposit_fields_of_deposit_with_mint() {
+        assert_eq!(
+            op_analyzer().deposit_fields(&deposit(Some("0xde0b6b3a7640000"))),
+            (
+                Some(SOURCE_HASH),
+                Some(uint!(1_000_000_000_000_000_000_U256))
+            )
+        );
+    }
+
+    #[test]
+    fn deposit_fields_of_deposit_without_mint() {
+        assert_eq!(
+            op_analyzer().deposit_fields(&deposit(None)),
+            (Some(SOURCE_HASH), None)
+        );
+    }
+
+    #[test]
+    fn deposit_fields_of_other_transactions() {
+        let legacy = transaction(json!({
+            "type": "0x0",
+            "gasPrice": "0x1",
+            "r": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "s": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "v": "0x1b",
+        }));
+        assert_eq!(op_analyzer().deposit_fields(&legacy), (None, None));
+        // 0x7e is no deposit type on Ethereum
+        let ethereum = BlockAnalyzer::new(AnalysisConfig::default());
+        assert_eq!(ethereum.deposit_fields(&deposit(None)), (None, None));
+    }
+
+    /// ArbSys, a system address of the Arbitrum profiles
+    const ARB_SYS: Address = address!("0x0000000000000000000000000000000000000064");
+
+    fn analysis_config(toml: &str) -> AnalysisConfig {
+        config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn custom_chain_profile_replaces_builtin_one() {
+        let analyzer = BlockAnalyzer::new(analysis_config(
+            r#"
+            chain_id = 42161
+            [[chain_profiles]]
+            chain_id = 42161
+            name = "arbitrum-one-custom"
+            block_time_ms = 1000
+            system_addresses = ["0x00000000000000000000000000000000000000cc"]
+            "#,
+        ));
+
+        assert_eq!(analyzer.chain_profile.name, "arbitrum-one-custom");
+        assert_eq!(analyzer.chain_profile.block_time_ms, 1000);
+        // nothing of the built-in profile is kept
+        assert!(analyzer.chain_profile.system_tx_types.is_empty());
+        assert!(!analyzer.is_system_tx(0, 0x6a));
+        assert_eq!(
+            analyzer.dependency_rules(None).ignored_addresses,
+            HashSet::from([address!("0x00000000000000000000000000000000000000cc")])
+        );
+        // other chains keep their built-in profile
+        assert_eq!(
+            chain_profile(8453, &analyzer.config.chain_profiles).name,
+            "base"
+        );
+    }
+
+    #[test]
+    fn system_transactions_and_addresses_stay_out_of_the_dag() {
+        let analyzer = BlockAnalyzer::new(AnalysisConfig {
+            chain_id: 42161,
+            ..Default::default()
+        });
+        // the internal transaction at 0 and two user transactions touching
+        // the same system account and each other's sender
+        let sender = address!("0x00000000000000000000000000000000000000a0");
+        let writes = |balance_set: Vec<Address>| TransactionStateSet {
+            read_set: StateSet {
+                balance_set: balance_set.iter().copied().collect(),
+                ..Default::default()
+            },
+            write_set: StateSet {
+                balance_set: balance_set.into_iter().collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tx_states = BTreeMap::from([
+            (0, writes(vec![sender, ARB_SYS])),
+            (1, writes(vec![ARB_SYS])),
+            (2, writes(vec![sender, ARB_SYS])),
+        ]);
+        let system_txs: HashSet<_> = [(0, 0x6a), (1, 0x2), (2, 0x2)]
+            .into_iter()
+            .filter(|(tx_index, tx_type)| analyzer.is_system_tx(*tx_index, *tx_type))
+            .map(|(tx_index, _)| tx_index)
+            .collect();
+        assert_eq!(system_txs, HashSet::from([0]));
+
+        let dag_tx_states = analyzer.select_dag_tx_states(&tx_states, &system_txs, vec![]);
+        assert_eq!(
+            dag_tx_states.keys().copied().collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(build_transaction_dag(&dag_tx_states, &analyzer.dependency_rules(None)).is_empty());
+        // without the profile, the system account links 2 to 1
+        assert_eq!(
+            build_transaction_dag(&dag_tx_states, &DependencyRules::default()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn l1_attributes_deposit_is_a_system_transaction_only_when_excluded() {
+        assert!(!op_analyzer().is_system_tx(0, 0x7e));
+        let analyzer = BlockAnalyzer::new(AnalysisConfig {
+            chain_id: 10,
+            exclude_l1_attributes_tx: true,
+            ..Default::default()
+        });
+        assert!(analyzer.is_system_tx(0, 0x7e));
+        assert!(!analyzer.is_system_tx(1, 0x7e));
+        assert!(!analyzer.is_system_tx(0, 0x2));
+    }
+}
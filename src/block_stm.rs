@@ -0,0 +1,366 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap},
+};
+
+use serde::Serialize;
+
+use crate::parallel_analyzer::{DependencyRules, StateKey, StateSet, TransactionStateSet};
+
+/// Outcome of replaying a block through the Block-STM model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockStmSim {
+    pub threads: usize,
+    /// gas-time until the last transaction is executed and validated
+    pub makespan_gas: u64,
+    /// executions aborted by a failed validation
+    pub aborts: u64,
+    /// gas spent on aborted executions
+    pub wasted_gas: u64,
+    /// total gas / makespan_gas, 1 for blocks without gas
+    pub speedup: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// waiting for a thread, writes of an earlier incarnation are estimates
+    Pending,
+    /// writes of an earlier incarnation, if any, are estimates
+    Executing,
+    /// writes of the current incarnation are visible
+    Executed,
+}
+
+/// Version a read resolved to: the writing transaction and its incarnation,
+/// or `None` for the pre-block state.
+type Version = Option<(usize, u32)>;
+
+/// Block-STM over positions `0..n` sorted by transaction index, with the
+/// recorded read/write sets standing in for every incarnation.
+struct BlockStm<'a> {
+    gas: &'a [u64],
+    reads: &'a [Vec<usize>],
+    writes: &'a [Vec<usize>],
+    /// transactions reading each key
+    readers: &'a [Vec<usize>],
+    status: Vec<Status>,
+    incarnation: Vec<u32>,
+    /// transactions that have written each key at least once
+    writers: Vec<BTreeSet<usize>>,
+    /// versions observed by the latest execution of each transaction
+    observed: Vec<Vec<(usize, Version)>>,
+    /// transactions suspended on an estimate written by each transaction
+    waiting: Vec<Vec<usize>>,
+    ready: BTreeSet<usize>,
+    aborts: u64,
+    wasted_gas: u64,
+}
+
+enum Read {
+    Version(Version),
+    /// the latest earlier writer is being re-executed
+    Estimate(usize),
+}
+
+impl BlockStm<'_> {
+    fn read(&self, tx: usize, key: usize) -> Read {
+        match self.writers[key].range(..tx).next_back() {
+            None => Read::Version(None),
+            Some(writer) if self.status[*writer] == Status::Executed => {
+                Read::Version(Some((*writer, self.incarnation[*writer])))
+            }
+            Some(writer) => Read::Estimate(*writer),
+        }
+    }
+
+    /// Starts an incarnation, or suspends the transaction if it would read an
+    /// estimate. Returns whether the execution started.
+    fn start(&mut self, tx: usize) -> bool {
+        let mut observed = Vec::with_capacity(self.reads[tx].len());
+        for key in &self.reads[tx] {
+            match self.read(tx, *key) {
+                Read::Version(version) => observed.push((*key, version)),
+                Read::Estimate(writer) => {
+                    self.waiting[writer].push(tx);
+                    return false;
+                }
+            }
+        }
+        self.observed[tx] = observed;
+        self.status[tx] = Status::Executing;
+        true
+    }
+
+    fn is_valid(&self, tx: usize) -> bool {
+        self.observed[tx]
+            .iter()
+            .all(|(key, version)| matches!(self.read(tx, *key), Read::Version(v) if v == *version))
+    }
+
+    fn abort(&mut self, tx: usize) {
+        self.aborts += 1;
+        self.wasted_gas += self.gas[tx];
+        self.incarnation[tx] += 1;
+        self.status[tx] = Status::Pending;
+        self.ready.insert(tx);
+    }
+
+    /// Publishes the writes of a finished execution, validates it and
+    /// re-validates later transactions that read what it wrote.
+    fn finish(&mut self, tx: usize) {
+        for key in &self.writes[tx] {
+            self.writers[*key].insert(tx);
+        }
+        self.status[tx] = Status::Executed;
+        for waiter in std::mem::take(&mut self.waiting[tx]) {
+            self.ready.insert(waiter);
+        }
+        if !self.is_valid(tx) {
+            self.abort(tx);
+            return;
+        }
+        let mut dependents = BTreeSet::new();
+        for key in &self.writes[tx] {
+            dependents.extend(self.readers[*key].iter().filter(|reader| **reader > tx));
+        }
+        for dependent in dependents {
+            if self.status[dependent] == Status::Executed && !self.is_valid(dependent) {
+                self.abort(dependent);
+            }
+        }
+    }
+
+    fn run(&mut self, threads: usize) -> u64 {
+        let mut running = BinaryHeap::new();
+        let mut now = 0;
+        loop {
+            while running.len() < threads {
+                let Some(tx) = self.ready.pop_first() else {
+                    break;
+                };
+                if self.start(tx) {
+                    running.push(Reverse((now + self.gas[tx], tx)));
+                }
+            }
+            let Some(Reverse((finish, tx))) = running.pop() else {
+                break;
+            };
+            now = finish;
+            self.finish(tx);
+        }
+        now
+    }
+}
+
+/// Replays a block through a Block-STM model for each thread count:
+/// transactions execute optimistically in index order, publish their writes
+/// to a multi-version memory, and are aborted and re-executed when a read
+/// they made is overwritten by an earlier transaction. Execution costs the
+/// transaction's gas used, validation is free. Keys ignored by `rules` are
+/// left out of the multi-version memory.
+pub fn simulate_block_stm(
+    tx_states: &BTreeMap<i64, TransactionStateSet>,
+    gas_used: &BTreeMap<i64, u64>,
+    rules: &DependencyRules,
+    threads: &[usize],
+) -> Vec<BlockStmSim> {
+    let mut key_ids: HashMap<StateKey, usize> = HashMap::new();
+    let mut key_id = |key| {
+        let next = key_ids.len();
+        *key_ids.entry(key).or_insert(next)
+    };
+    let mut reads = Vec::with_capacity(tx_states.len());
+    let mut writes = Vec::with_capacity(tx_states.len());
+    let mut gas = Vec::with_capacity(tx_states.len());
+    for (tx_index, state) in tx_states {
+        let mut keys = |set: &StateSet| {
+            set.keys()
+                .filter(|key| !rules.ignores(key))
+                .map(&mut key_id)
+                .collect::<Vec<_>>()
+        };
+        reads.push(keys(&state.read_set));
+        writes.push(keys(&state.write_set));
+        gas.push(gas_used.get(tx_index).copied().unwrap_or_default());
+    }
+    let mut readers = vec![Vec::new(); key_ids.len()];
+    for (tx, keys) in reads.iter().enumerate() {
+        for key in keys {
+            readers[*key].push(tx);
+        }
+    }
+    let total_gas: u64 = gas.iter().sum();
+    threads
+        .iter()
+        .map(|threads| {
+            let threads = (*threads).max(1);
+            let mut stm = BlockStm {
+                gas: &gas,
+                reads: &reads,
+                writes: &writes,
+                readers: &readers,
+                status: vec![Status::Pending; gas.len()],
+                incarnation: vec![0; gas.len()],
+                writers: vec![BTreeSet::new(); readers.len()],
+                observed: vec![Vec::new(); gas.len()],
+                waiting: vec![Vec::new(); gas.len()],
+                ready: (0..gas.len()).collect(),
+                aborts: 0,
+                wasted_gas: 0,
+            };
+            let makespan_gas = stm.run(threads);
+            BlockStmSim {
+                threads,
+                makespan_gas,
+                aborts: stm.aborts,
+                wasted_gas: stm.wasted_gas,
+                speedup: if makespan_gas == 0 {
+                    1.0
+                } else {
+                    total_gas as f64 / makespan_gas as f64
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy::primitives::{Address, B256, address};
+
+    use super::*;
+
+    const CONTRACT: Address = address!("0x00000000000000000000000000000000000000cc");
+
+    /// Storage slots of `CONTRACT`.
+    fn slots(slots: &[u8]) -> StateSet {
+        StateSet {
+            storage_set: slots
+                .iter()
+                .map(|slot| (CONTRACT, B256::with_last_byte(*slot)))
+                .collect(),
+            ..StateSet::default()
+        }
+    }
+
+    fn tx_states(txs: &[(&[u8], &[u8])]) -> BTreeMap<i64, TransactionStateSet> {
+        txs.iter()
+            .enumerate()
+            .map(|(tx_index, (reads, writes))| {
+                (
+                    tx_index as i64,
+                    TransactionStateSet {
+                        read_set: slots(reads),
+                        write_set: slots(writes),
+                        authorities: HashSet::new(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn gas_used(gas: &[u64]) -> BTreeMap<i64, u64> {
+        gas.iter()
+            .enumerate()
+            .map(|(tx_index, gas)| (tx_index as i64, *gas))
+            .collect()
+    }
+
+    #[test]
+    fn independent_block_scales_with_threads() {
+        let tx_states = tx_states(&[(&[], &[0]), (&[], &[1]), (&[], &[2]), (&[], &[3])]);
+        let sims = simulate_block_stm(
+            &tx_states,
+            &gas_used(&[100, 100, 100, 100]),
+            &DependencyRules::default(),
+            &[1, 2, 4],
+        );
+
+        assert_eq!(
+            sims.iter()
+                .map(|sim| (sim.threads, sim.makespan_gas, sim.aborts, sim.speedup))
+                .collect::<Vec<_>>(),
+            vec![(1, 400, 0, 1.0), (2, 200, 0, 2.0), (4, 100, 0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn chain_runs_sequentially() {
+        // every transaction reads and writes slot 0
+        let tx_states = tx_states(&[(&[0], &[0]), (&[0], &[0]), (&[0], &[0])]);
+        let sims = simulate_block_stm(
+            &tx_states,
+            &gas_used(&[100, 100, 100]),
+            &DependencyRules::default(),
+            &[3],
+        );
+
+        // tx 1 is aborted once tx 0 publishes, tx 2 once it reads the
+        // estimate of tx 1's re-execution
+        assert_eq!(
+            sims,
+            vec![BlockStmSim {
+                threads: 3,
+                makespan_gas: 300,
+                aborts: 2,
+                wasted_gas: 200,
+                speedup: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn read_after_write_aborts_the_reader_once() {
+        // tx 1 reads slot 0 before the longer tx 0 writes it
+        let tx_states = tx_states(&[(&[], &[0]), (&[0], &[])]);
+        let sims = simulate_block_stm(
+            &tx_states,
+            &gas_used(&[100, 50]),
+            &DependencyRules::default(),
+            &[2],
+        );
+
+        assert_eq!(
+            sims,
+            vec![BlockStmSim {
+                threads: 2,
+                makespan_gas: 150,
+                aborts: 1,
+                wasted_gas: 50,
+                speedup: 1.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_block() {
+        let sims = simulate_block_stm(
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+            &DependencyRules::default(),
+            &[1, 4],
+        );
+
+        assert_eq!(
+            sims,
+            vec![
+                BlockStmSim {
+                    threads: 1,
+                    makespan_gas: 0,
+                    aborts: 0,
+                    wasted_gas: 0,
+                    speedup: 1.0,
+                },
+                BlockStmSim {
+                    threads: 4,
+                    makespan_gas: 0,
+                    aborts: 0,
+                    wasted_gas: 0,
+                    speedup: 1.0,
+                },
+            ]
+        );
+    }
+}
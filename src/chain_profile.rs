@@ -0,0 +1,127 @@
+use alloy::primitives::{Address, address};
+use serde_derive::Deserialize;
+
+/// Chain-specific knowledge the analyzer needs, keyed by `chain_id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainProfile {
+    pub chain_id: i64,
+    pub name: String,
+    /// expected time between blocks, used to poll for new blocks
+    pub block_time_ms: u64,
+    /// system contracts and accounts whose state never causes a dependency
+    #[serde(default)]
+    pub system_addresses: Vec<Address>,
+    /// types of protocol-injected transactions, stored but left out of the DAG
+    #[serde(default)]
+    pub system_tx_types: Vec<u8>,
+    /// type of L1-originated deposit transactions, stored with their source
+    /// hash and minted value
+    #[serde(default)]
+    pub deposit_tx_type: Option<u8>,
+    /// accounts collecting fees, excluded with `lazy_fee.exclude_fee_vaults`
+    #[serde(default)]
+    pub fee_vaults: Vec<Address>,
+}
+
+/// EIP-4788 beacon roots contract
+pub const BEACON_ROOTS: Address = address!("0x000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+/// EIP-2935 history storage contract
+pub const HISTORY_STORAGE: Address = address!("0x0000F90827F1C53a10cb7A02335B175320002935");
+/// caller of the EIP-4788 and EIP-2935 system calls
+const SYSTEM_ADDRESS: Address = address!("0xfffffffffffffffffffffffffffffffffffffffe");
+
+/// sender of the OP-stack L1 attributes transaction
+const OP_DEPOSITOR_ACCOUNT: Address = address!("0xDeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001");
+const OP_GAS_PRICE_ORACLE: Address = address!("0x420000000000000000000000000000000000000F");
+const OP_L1_BLOCK: Address = address!("0x4200000000000000000000000000000000000015");
+const OP_SEQUENCER_FEE_VAULT: Address = address!("0x4200000000000000000000000000000000000011");
+const OP_BASE_FEE_VAULT: Address = address!("0x4200000000000000000000000000000000000019");
+const OP_L1_FEE_VAULT: Address = address!("0x420000000000000000000000000000000000001A");
+const OP_OPERATOR_FEE_VAULT: Address = address!("0x420000000000000000000000000000000000001b");
+
+const ARB_SYS: Address = address!("0x0000000000000000000000000000000000000064");
+const ARB_GAS_INFO: Address = address!("0x000000000000000000000000000000000000006C");
+const ARB_RETRYABLE_TX: Address = address!("0x000000000000000000000000000000000000006E");
+/// account holding the ArbOS state
+const ARB_OS_STATE: Address = address!("0xA4B05FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF");
+/// Arbitrum internal transaction type, one per block to update L1 pricing
+const ARB_INTERNAL_TX_TYPE: u8 = 0x6a;
+/// OP-stack deposit transaction type, the first deposit of every block sets
+/// the L1 attributes
+const OP_DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+fn ethereum(chain_id: i64, name: &str) -> ChainProfile {
+    ChainProfile {
+        chain_id,
+        name: name.to_string(),
+        block_time_ms: 12_000,
+        system_addresses: vec![BEACON_ROOTS, HISTORY_STORAGE, SYSTEM_ADDRESS],
+        system_tx_types: vec![],
+        deposit_tx_type: None,
+        fee_vaults: vec![],
+    }
+}
+
+fn op_stack(chain_id: i64, name: &str) -> ChainProfile {
+    ChainProfile {
+        chain_id,
+        name: name.to_string(),
+        block_time_ms: 2_000,
+        system_addresses: vec![OP_DEPOSITOR_ACCOUNT, OP_GAS_PRICE_ORACLE, OP_L1_BLOCK],
+        system_tx_types: vec![],
+        deposit_tx_type: Some(OP_DEPOSIT_TX_TYPE),
+        fee_vaults: vec![
+            OP_SEQUENCER_FEE_VAULT,
+            OP_BASE_FEE_VAULT,
+            OP_L1_FEE_VAULT,
+            OP_OPERATOR_FEE_VAULT,
+        ],
+    }
+}
+
+fn arbitrum(chain_id: i64, name: &str) -> ChainProfile {
+    ChainProfile {
+        chain_id,
+        name: name.to_string(),
+        block_time_ms: 250,
+        system_addresses: vec![ARB_SYS, ARB_GAS_INFO, ARB_RETRYABLE_TX, ARB_OS_STATE],
+        system_tx_types: vec![ARB_INTERNAL_TX_TYPE],
+        deposit_tx_type: None,
+        fee_vaults: vec![],
+    }
+}
+
+/// Profiles shipped with the analyzer.
+pub fn builtin_profiles() -> Vec<ChainProfile> {
+    vec![
+        ethereum(1, "ethereum"),
+        ethereum(17000, "holesky"),
+        ethereum(11155111, "sepolia"),
+        op_stack(10, "op-mainnet"),
+        op_stack(8453, "base"),
+        op_stack(11155420, "op-sepolia"),
+        op_stack(84532, "base-sepolia"),
+        arbitrum(42161, "arbitrum-one"),
+        arbitrum(421614, "arbitrum-sepolia"),
+    ]
+}
+
+/// Returns the profile for `chain_id`, preferring `custom` profiles over the
+/// built-in ones. Unknown chains are treated like Ethereum without any system
+/// contracts.
+pub fn chain_profile(chain_id: i64, custom: &[ChainProfile]) -> ChainProfile {
+    custom
+        .iter()
+        .cloned()
+        .chain(builtin_profiles())
+        .find(|profile| profile.chain_id == chain_id)
+        .unwrap_or_else(|| ChainProfile {
+            chain_id,
+            name: "unknown".to_string(),
+            block_time_ms: 12_000,
+            system_addresses: vec![],
+            system_tx_types: vec![],
+            deposit_tx_type: None,
+            fee_vaults: vec![],
+        })
+}
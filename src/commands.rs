@@ -0,0 +1,249 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use eyre::{Result, eyre};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    config::Config,
+    db::{
+        DB,
+        block::{Block, BlockDB},
+        block_parallelism_stats::{BlockParallelismStats, BlockParallelismStatsDB},
+        failed_block::FailedBlockDB,
+        parallel_analyzer_state::{ParallelAnalyzerState, ParallelAnalyzerStateDB},
+        transaction_dag::{TransactionDag, TransactionDagDB},
+    },
+    parallel_analyzer::{ParallelAnalyzer, queue_uncovered_ranges},
+    rpc_cache::RpcCacheMode,
+    server::ServerState,
+};
+
+/// Queues the blocks a raised `start_block` makes the head follower skip as
+/// backfill ranges, leaving out those already queued.
+pub async fn queue_skipped_blocks(db: &DB, config: &Config) -> Result<()> {
+    let Some(state) = db
+        .get_parallel_analyzer_state_by_chainid(config.chain_id)
+        .await?
+    else {
+        return Ok(());
+    };
+    let resume_block = state.latest_analyzed_block + 1;
+    if config.start_block <= resume_block {
+        return Ok(());
+    }
+    let queued = queue_uncovered_ranges(
+        db,
+        config.chain_id,
+        &config.backfill,
+        resume_block.max(config.backfill.from_block),
+        config.start_block - 1,
+    )
+    .await?;
+    if queued > 0 {
+        info!(
+            "start_block {} skips blocks {} to {}, queued as {} backfill ranges",
+            config.start_block,
+            resume_block,
+            config.start_block - 1,
+            queued
+        );
+    }
+    Ok(())
+}
+
+/// Creates the analyzer state on the first run and returns the block the head
+/// follower resumes from.
+pub async fn init_analyzer_state(db: &DB, config: &Config) -> Result<i64> {
+    let parallel_analyzer_state = db
+        .get_parallel_analyzer_state_by_chainid(config.chain_id)
+        .await?;
+    let start_block = if let Some(state) = parallel_analyzer_state {
+        std::cmp::max(state.latest_analyzed_block + 1, config.start_block)
+    } else {
+        db.insert_parallel_analyzer_state(&ParallelAnalyzerState {
+            latest_block: 0,
+            chain_id: config.chain_id,
+            start_block: config.start_block,
+            latest_analyzed_block: config.start_block - 1,
+            horizon: config.analysis_horizon.to_string(),
+            created_at: None,
+            updated_at: None,
+        })
+        .await?;
+        config.start_block
+    };
+    Ok(start_block)
+}
+
+/// Fails in replay mode, where the head follower would poll a head the rpc
+/// cache never serves.
+fn ensure_head_readable(config: &Config) -> Result<()> {
+    if config.rpc_cache.mode == RpcCacheMode::Replay {
+        return Err(eyre!(
+            "the head is not replayed from the rpc cache, replay only supports analyze --from-block and reanalyze"
+        ));
+    }
+    Ok(())
+}
+
+/// Runs the API server and the analyzer together.
+pub async fn run(db: Arc<DB>, config: Config) -> Result<()> {
+    ensure_head_readable(&config)?;
+    let server_state = ServerState::new(db.clone(), config.clone())?;
+    queue_skipped_blocks(&db, &config).await?;
+    let start_block = init_analyzer_state(&db, &config).await?;
+    let parallel_analyzer = ParallelAnalyzer::new(db, config, start_block);
+    let _ = tokio::join!(
+        server_state.run(),
+        parallel_analyzer.run(),
+        parallel_analyzer.run_backfill()
+    );
+    Ok(())
+}
+
+pub async fn serve(db: Arc<DB>, config: Config) -> Result<()> {
+    ServerState::new(db, config)?.run().await
+}
+
+/// Follows the chain head, or analyzes `from_block` to `to_block` and exits.
+pub async fn analyze(
+    db: Arc<DB>,
+    config: Config,
+    from_block: Option<i64>,
+    to_block: Option<i64>,
+) -> Result<()> {
+    if from_block.is_none() {
+        ensure_head_readable(&config)?;
+        queue_skipped_blocks(&db, &config).await?;
+    }
+    let start_block = init_analyzer_state(&db, &config).await?;
+    let parallel_analyzer = ParallelAnalyzer::new(db, config, start_block);
+    let Some(from_block) = from_block else {
+        let (head, backfill) =
+            tokio::join!(parallel_analyzer.run(), parallel_analyzer.run_backfill());
+        return head.and(backfill);
+    };
+    let to_block = to_block.unwrap_or(from_block);
+    let processed = parallel_analyzer
+        .analyze_range(from_block, to_block, false)
+        .await?;
+    info!(
+        "Analysed {} blocks from {} to {}",
+        processed, from_block, to_block
+    );
+    Ok(())
+}
+
+pub async fn backfill(db: Arc<DB>, mut config: Config, workers: Option<usize>) -> Result<()> {
+    if let Some(workers) = workers {
+        config.backfill.workers = workers;
+    }
+    if config.backfill.workers == 0 {
+        info!("No backfill workers configured");
+        return Ok(());
+    }
+    queue_skipped_blocks(&db, &config).await?;
+    let start_block = init_analyzer_state(&db, &config).await?;
+    ParallelAnalyzer::new(db, config, start_block)
+        .run_backfill()
+        .await
+}
+
+pub async fn reanalyze(
+    db: Arc<DB>,
+    config: Config,
+    from_block: i64,
+    to_block: Option<i64>,
+    from_db: bool,
+) -> Result<()> {
+    let to_block = to_block.unwrap_or(from_block);
+    let analyzer = ParallelAnalyzer::new(db, config, from_block);
+    let processed = if from_db {
+        analyzer
+            .reanalyze_stored_range(from_block, to_block)
+            .await?
+    } else {
+        analyzer.analyze_range(from_block, to_block, true).await?
+    };
+    info!(
+        "Re-analysed {} blocks from {} to {}",
+        processed, from_block, to_block
+    );
+    Ok(())
+}
+
+/// One line of `export`.
+#[derive(Serialize)]
+struct ExportedBlock {
+    block: Block,
+    stats: Option<BlockParallelismStats>,
+    dags: Vec<TransactionDag>,
+}
+
+/// Writes every stored block from `from_block` to `to_block` as a JSON line.
+pub async fn export(
+    db: Arc<DB>,
+    from_block: i64,
+    to_block: Option<i64>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let to_block = to_block.unwrap_or(from_block);
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut exported = 0;
+    for block_number in from_block..=to_block {
+        let Some(block) = db.get_block_by_number(block_number).await? else {
+            continue;
+        };
+        let line = ExportedBlock {
+            block,
+            stats: db
+                .get_block_parallelism_stats_by_block_number(block_number)
+                .await?,
+            dags: db
+                .get_transaction_dags_by_block_number(block_number)
+                .await?,
+        };
+        serde_json::to_writer(&mut writer, &line)?;
+        writeln!(writer)?;
+        exported += 1;
+    }
+    writer.flush()?;
+    info!(
+        "Exported {} blocks from {} to {}",
+        exported, from_block, to_block
+    );
+    Ok(())
+}
+
+pub async fn migrate(db: Arc<DB>) -> Result<()> {
+    sqlx::migrate!().run(&db.db).await?;
+    info!("Database migrations applied");
+    Ok(())
+}
+
+pub async fn retry_failed(db: Arc<DB>, config: Config, limit: i64) -> Result<()> {
+    let block_numbers: Vec<i64> = db
+        .get_failed_blocks_by_chainid(config.chain_id, limit)
+        .await?
+        .into_iter()
+        .map(|b| b.block_number)
+        .collect();
+    let recovered = ParallelAnalyzer::new(db, config, 0)
+        .retry_failed_blocks(&block_numbers)
+        .await?;
+    info!(
+        "Recovered {} of {} failed blocks",
+        recovered,
+        block_numbers.len()
+    );
+    Ok(())
+}
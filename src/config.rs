@@ -1,16 +1,96 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf, str::FromStr};
 
-use clap::Parser;
+use alloy::primitives::Address;
+use clap::{Parser, Subcommand, ValueEnum};
 use config::{Config as FileConfig, ConfigError, Environment, File};
 use reqwest::Url;
 use serde_derive::Deserialize;
 
+use crate::{chain_profile::ChainProfile, rpc_cache::RpcCacheConfig};
+
 #[derive(Clone, Parser)]
 pub struct Cli {
     #[clap(short, long)]
     pub config: Option<PathBuf>,
     #[clap(short, long, default_value = "false")]
     pub version: bool,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+// without a subcommand the API server and the analyzer run together
+#[derive(Clone, Subcommand)]
+pub enum Command {
+    #[clap(flatten)]
+    Database(DatabaseCommand),
+    /// Analyze recorded blocks and their traces from a JSON file without the
+    /// database or the execution api
+    AnalyzeFile {
+        /// a recorded block, or a list of them, with `block`, `receipts`,
+        /// `prestate_traces` and `diff_traces`
+        path: PathBuf,
+        #[clap(long, value_enum, default_value = "table")]
+        format: OutputFormat,
+    },
+}
+
+// subcommands that open the database
+#[derive(Clone, Subcommand)]
+pub enum DatabaseCommand {
+    /// Serve the API without analyzing blocks
+    Serve,
+    /// Follow the chain head and run the backfill workers without the API, or
+    /// analyze a block range and exit
+    Analyze {
+        /// first block of the range, analyzed alone without --to-block
+        #[clap(long)]
+        from_block: Option<i64>,
+        #[clap(long, requires = "from_block")]
+        to_block: Option<i64>,
+    },
+    /// Run the backfill workers until every range is analyzed and exit
+    Backfill {
+        /// overrides backfill.workers
+        #[clap(long)]
+        workers: Option<usize>,
+    },
+    /// Re-analyze a block range, replacing its DAG rows, and exit
+    Reanalyze {
+        #[clap(long)]
+        from_block: i64,
+        /// defaults to --from-block
+        #[clap(long)]
+        to_block: Option<i64>,
+        /// rebuild the DAGs from the stored read/write sets instead of
+        /// tracing the blocks again
+        #[clap(long)]
+        from_db: bool,
+    },
+    /// Export a block range with its stats and DAG edges as JSON lines
+    Export {
+        #[clap(long)]
+        from_block: i64,
+        /// defaults to --from-block
+        #[clap(long)]
+        to_block: Option<i64>,
+        /// file to write, stdout if not set
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// Apply the database migrations and exit
+    Migrate,
+    /// Re-analyze the blocks recorded in failed_blocks and exit
+    RetryFailed {
+        /// maximum number of failed blocks to retry
+        #[clap(long, default_value = "1000")]
+        limit: i64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -19,7 +99,273 @@ pub struct Config {
     pub start_block: i64,
     pub chain_id: i64,
     pub server_addr: String,
+    /// bearer token of the admin endpoints, which are disabled without one
+    #[serde(default)]
+    pub admin_token: Option<String>,
     pub database_url: String,
+    /// head the analyzer follows: "latest", "safe", "finalized" or
+    /// "latest-N"
+    #[serde(default)]
+    pub analysis_horizon: AnalysisHorizon,
+    /// maximum number of trace requests in flight against `execution_api`
+    #[serde(default = "default_trace_concurrency")]
+    pub trace_concurrency: usize,
+    /// number of blocks fetched and traced ahead of the block being stored,
+    /// 0 fetches every block only once the previous one is stored
+    #[serde(default = "default_block_prefetch")]
+    pub block_prefetch: usize,
+    /// deepest reorg the analyzer walks back through before giving up
+    #[serde(default = "default_max_reorg_depth")]
+    pub max_reorg_depth: i64,
+    /// retries of blocks that fail with a retryable error
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// records block, receipt and trace responses, or replays them without
+    /// the node
+    #[serde(default)]
+    pub rpc_cache: RpcCacheConfig,
+    /// historical blocks analyzed by a worker pool alongside the head follower
+    #[serde(default)]
+    pub backfill: BackfillConfig,
+    /// worker counts the analyzer simulates a schedule for on every block
+    #[serde(default = "default_simulated_workers")]
+    pub simulated_workers: Vec<usize>,
+    /// balances left out of dependency detection, as a parallel EVM with lazy
+    /// fee accounting would
+    #[serde(default)]
+    pub lazy_fee: LazyFeeConfig,
+    /// leave the L1 attributes deposit that opens every OP-stack block out of
+    /// the DAG
+    #[serde(default)]
+    pub exclude_l1_attributes_tx: bool,
+    /// add the pre-block system calls (index -1) and the withdrawals (index
+    /// tx count) to the DAG as zero-gas pseudo-transactions
+    #[serde(default)]
+    pub pseudo_transactions: bool,
+    /// profiles added to, or replacing, the built-in chain profiles
+    #[serde(default)]
+    pub chain_profiles: Vec<ChainProfile>,
+}
+
+/// The part of `Config` the dependency analysis reads, loaded on its own by
+/// `analyze-file` so it needs neither the database nor the execution api.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnalysisConfig {
+    #[serde(default = "default_chain_id")]
+    pub chain_id: i64,
+    #[serde(default = "default_simulated_workers")]
+    pub simulated_workers: Vec<usize>,
+    #[serde(default)]
+    pub lazy_fee: LazyFeeConfig,
+    #[serde(default)]
+    pub exclude_l1_attributes_tx: bool,
+    #[serde(default)]
+    pub pseudo_transactions: bool,
+    #[serde(default)]
+    pub chain_profiles: Vec<ChainProfile>,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: default_chain_id(),
+            simulated_workers: default_simulated_workers(),
+            lazy_fee: LazyFeeConfig::default(),
+            exclude_l1_attributes_tx: false,
+            pseudo_transactions: false,
+            chain_profiles: Vec::new(),
+        }
+    }
+}
+
+impl From<&Config> for AnalysisConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            chain_id: config.chain_id,
+            simulated_workers: config.simulated_workers.clone(),
+            lazy_fee: config.lazy_fee.clone(),
+            exclude_l1_attributes_tx: config.exclude_l1_attributes_tx,
+            pseudo_transactions: config.pseudo_transactions,
+            chain_profiles: config.chain_profiles.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AnalysisHorizon {
+    #[default]
+    Latest,
+    Safe,
+    Finalized,
+    /// N blocks behind latest
+    LatestMinus(u64),
+}
+
+impl FromStr for AnalysisHorizon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Self::Latest),
+            "safe" => Ok(Self::Safe),
+            "finalized" => Ok(Self::Finalized),
+            _ => s
+                .strip_prefix("latest-")
+                .and_then(|n| n.parse().ok())
+                .map(Self::LatestMinus)
+                .ok_or_else(|| format!("invalid analysis_horizon {s:?}")),
+        }
+    }
+}
+
+impl TryFrom<String> for AnalysisHorizon {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for AnalysisHorizon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::Safe => write!(f, "safe"),
+            Self::Finalized => write!(f, "finalized"),
+            Self::LatestMinus(n) => write!(f, "latest-{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryConfig {
+    /// attempts per block before it is recorded in `failed_blocks`
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// backoff before the first retry, doubled on every further retry
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackfillConfig {
+    /// backfill workers, 0 disables backfill
+    #[serde(default)]
+    pub workers: usize,
+    /// lowest block to backfill, up to the head follower's first block
+    #[serde(default)]
+    pub from_block: i64,
+    /// blocks in a range claimed by one worker at a time
+    #[serde(default = "default_range_size")]
+    pub range_size: i64,
+    #[serde(default)]
+    pub direction: BackfillDirection,
+    /// a running range not advanced for this long is claimed by another
+    /// worker
+    #[serde(default = "default_stale_range_secs")]
+    pub stale_range_secs: i64,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            workers: 0,
+            from_block: 0,
+            range_size: default_range_size(),
+            direction: BackfillDirection::default(),
+            stale_range_secs: default_stale_range_secs(),
+        }
+    }
+}
+
+/// Order in which backfill ranges, and the blocks within them, are analyzed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackfillDirection {
+    /// newest history first, down to `from_block`
+    #[default]
+    Backward,
+    /// from `from_block` up
+    Forward,
+}
+
+impl BackfillDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Backward => "backward",
+            Self::Forward => "forward",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LazyFeeConfig {
+    /// the block's fee recipient (coinbase)
+    #[serde(default)]
+    pub exclude_fee_recipient: bool,
+    /// the zero address
+    #[serde(default)]
+    pub exclude_burn_address: bool,
+    /// the fee vaults of the chain profile
+    #[serde(default)]
+    pub exclude_fee_vaults: bool,
+    /// further fee vaults
+    #[serde(default)]
+    pub fee_vaults: Vec<Address>,
+}
+
+fn default_trace_concurrency() -> usize {
+    8
+}
+
+fn default_block_prefetch() -> usize {
+    4
+}
+
+fn default_max_reorg_depth() -> i64 {
+    64
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1_000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    60_000
+}
+
+fn default_range_size() -> i64 {
+    1_000
+}
+
+fn default_stale_range_secs() -> i64 {
+    600
+}
+
+// ethereum mainnet
+fn default_chain_id() -> i64 {
+    1
+}
+
+fn default_simulated_workers() -> Vec<usize> {
+    vec![2, 4, 8, 16, 32]
 }
 
 impl Config {
@@ -31,3 +377,56 @@ impl Config {
         c.try_deserialize()
     }
 }
+
+impl AnalysisConfig {
+    /// Reads the analysis settings of a config file, ignoring the others, or
+    /// the defaults without one.
+    pub fn new(config: Option<PathBuf>) -> Result<Self, ConfigError> {
+        let mut builder = FileConfig::builder();
+        if let Some(config) = config {
+            builder = builder.add_source(File::from(config));
+        }
+        builder
+            .add_source(Environment::with_prefix("PEVM"))
+            .build()?
+            .try_deserialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analysis_horizon_round_trips() {
+        for horizon in [
+            AnalysisHorizon::Latest,
+            AnalysisHorizon::Safe,
+            AnalysisHorizon::Finalized,
+            AnalysisHorizon::LatestMinus(0),
+            AnalysisHorizon::LatestMinus(5),
+        ] {
+            assert_eq!(horizon.to_string().parse(), Ok(horizon));
+        }
+        assert_eq!("latest-5".parse(), Ok(AnalysisHorizon::LatestMinus(5)));
+    }
+
+    #[test]
+    fn analysis_horizon_rejects_invalid_input() {
+        for s in [
+            "",
+            "Latest",
+            "latest-",
+            "latest- 5",
+            "latest-5 ",
+            "latest--5",
+            "latest-five",
+            "pending",
+        ] {
+            assert_eq!(
+                s.parse::<AnalysisHorizon>(),
+                Err(format!("invalid analysis_horizon {s:?}"))
+            );
+        }
+    }
+}
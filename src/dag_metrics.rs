@@ -0,0 +1,603 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, VecDeque},
+};
+
+use serde::Serialize;
+
+use crate::parallel_analyzer::{DEP_BALANCE, DEP_CODE, DEP_NONCE, DEP_STORAGE, DagEdge};
+
+/// Shape of a block's dependency DAG.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DagMetrics {
+    /// transactions in the DAG, pseudo-transactions left out
+    pub tx_count: i64,
+    /// pre-block system calls and withdrawals in the DAG
+    pub pseudo_tx_count: i64,
+    pub edge_count: i64,
+    /// edges by the resource that caused them, each counted once, see
+    /// `edge_resource`
+    pub balance_edge_count: i64,
+    pub code_edge_count: i64,
+    pub storage_edge_count: i64,
+    pub nonce_edge_count: i64,
+    /// number of transactions on the longest dependency chain,
+    /// pseudo-transactions left out; a transaction's topological level is
+    /// the longest chain ending at it, so this is also the level count
+    pub critical_path_length: i64,
+    /// largest set of transactions with no path between any two of them
+    pub max_width: i64,
+    /// weakly connected components, isolated transactions included
+    pub components: i64,
+    /// tx_count / critical_path_length, 1 for empty blocks
+    pub speedup: f64,
+    pub total_gas: i64,
+    /// gas of the dependency chain with the most gas used
+    pub critical_path_gas: i64,
+    /// total_gas / critical_path_gas, 1 for blocks without gas
+    pub gas_speedup: f64,
+}
+
+/// Result of list-scheduling a block's DAG on a fixed number of workers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduleSim {
+    pub workers: usize,
+    /// gas-time until the last transaction finishes
+    pub makespan_gas: u64,
+    /// share of worker gas-time spent executing transactions
+    pub utilization: f64,
+    /// total gas / makespan_gas, 1 for blocks without gas
+    pub speedup: f64,
+}
+
+/// Dependency DAG over positions `0..n`, where nodes are sorted by
+/// transaction index so every edge points to an earlier position.
+struct Dag {
+    deps: Vec<Vec<usize>>,
+    gas: Vec<u64>,
+}
+
+impl Dag {
+    fn new(nodes: &[(i64, u64)], edges: &[DagEdge]) -> Self {
+        let gas = nodes.iter().map(|(_, gas)| *gas).collect();
+        let nodes: Vec<i64> = nodes.iter().map(|(index, _)| *index).collect();
+        let mut deps = vec![Vec::new(); nodes.len()];
+        for edge in edges {
+            let (Ok(source), Ok(target)) = (
+                nodes.binary_search(&edge.source),
+                nodes.binary_search(&edge.target),
+            ) else {
+                continue;
+            };
+            if target < source {
+                deps[source].push(target);
+            }
+        }
+        Self { deps, gas }
+    }
+
+    fn len(&self) -> usize {
+        self.deps.len()
+    }
+
+    /// Counted nodes on the longest dependency chain ending at every node.
+    fn levels(&self, counted: &[bool]) -> Vec<i64> {
+        let mut levels = vec![0; self.len()];
+        for node in 0..self.len() {
+            levels[node] = counted[node] as i64
+                + self.deps[node]
+                    .iter()
+                    .map(|dep| levels[*dep])
+                    .max()
+                    .unwrap_or_default();
+        }
+        levels
+    }
+
+    /// Gas used up to and including every node along its heaviest
+    /// dependency chain.
+    fn gas_finish(&self) -> Vec<u64> {
+        let mut finish = vec![0; self.len()];
+        for node in 0..self.len() {
+            finish[node] = self.gas[node]
+                + self.deps[node]
+                    .iter()
+                    .map(|dep| finish[*dep])
+                    .max()
+                    .unwrap_or_default();
+        }
+        finish
+    }
+
+    /// Bitset of the nodes each node transitively depends on.
+    fn reachability(&self) -> Vec<Vec<u64>> {
+        let words = self.len().div_ceil(64);
+        let mut reach = vec![vec![0u64; words]; self.len()];
+        for node in 0..self.len() {
+            for dep in self.deps[node].clone() {
+                let (done, rest) = reach.split_at_mut(node);
+                let current = &mut rest[0];
+                current[dep / 64] |= 1 << (dep % 64);
+                for (word, dep_word) in current.iter_mut().zip(&done[dep]) {
+                    *word |= dep_word;
+                }
+            }
+        }
+        reach
+    }
+
+    /// Size of the maximum antichain. By Dilworth's theorem it equals the
+    /// node count minus a maximum matching in the transitive closure.
+    fn max_width(&self) -> usize {
+        self.len() - max_matching(&self.reachability())
+    }
+
+    /// Greedy list scheduling: whenever a worker is idle it takes the ready
+    /// transaction with the lowest index, which runs for its gas used.
+    fn schedule(&self, workers: usize) -> ScheduleSim {
+        let workers = workers.max(1);
+        let mut dependents = vec![Vec::new(); self.len()];
+        let mut pending: Vec<usize> = self.deps.iter().map(Vec::len).collect();
+        for (node, deps) in self.deps.iter().enumerate() {
+            for dep in deps {
+                dependents[*dep].push(node);
+            }
+        }
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.len())
+            .filter(|node| pending[*node] == 0)
+            .map(Reverse)
+            .collect();
+        let mut running = BinaryHeap::new();
+        let mut now = 0;
+        loop {
+            while running.len() < workers {
+                let Some(Reverse(node)) = ready.pop() else {
+                    break;
+                };
+                running.push(Reverse((now + self.gas[node], node)));
+            }
+            let Some(Reverse((finish, _))) = running.peek().copied() else {
+                break;
+            };
+            now = finish;
+            // release every transaction finishing now before picking the next
+            while let Some(Reverse((finish, node))) = running.peek().copied() {
+                if finish != now {
+                    break;
+                }
+                running.pop();
+                for dependent in &dependents[node] {
+                    pending[*dependent] -= 1;
+                    if pending[*dependent] == 0 {
+                        ready.push(Reverse(*dependent));
+                    }
+                }
+            }
+        }
+        let total_gas: u64 = self.gas.iter().sum();
+        let (utilization, speedup) = if now == 0 {
+            (0.0, 1.0)
+        } else {
+            (
+                total_gas as f64 / (workers as u64 * now) as f64,
+                total_gas as f64 / now as f64,
+            )
+        };
+        ScheduleSim {
+            workers,
+            makespan_gas: now,
+            utilization,
+            speedup,
+        }
+    }
+
+    fn components(&self) -> usize {
+        let mut parent: Vec<usize> = (0..self.len()).collect();
+        fn find(parent: &mut [usize], mut node: usize) -> usize {
+            while parent[node] != node {
+                parent[node] = parent[parent[node]];
+                node = parent[node];
+            }
+            node
+        }
+        let mut components = self.len();
+        for (node, deps) in self.deps.iter().enumerate() {
+            for dep in deps {
+                let (a, b) = (find(&mut parent, node), find(&mut parent, *dep));
+                if a != b {
+                    parent[a] = b;
+                    components -= 1;
+                }
+            }
+        }
+        components
+    }
+}
+
+/// Next node set in `bits` at or after `*from`, advancing `*from` past it.
+fn next_bit(bits: &[u64], from: &mut usize) -> Option<usize> {
+    while *from < bits.len() * 64 {
+        let word = bits[*from / 64] >> (*from % 64);
+        if word == 0 {
+            *from = (*from / 64 + 1) * 64;
+            continue;
+        }
+        let node = *from + word.trailing_zeros() as usize;
+        *from = node + 1;
+        return Some(node);
+    }
+    None
+}
+
+/// Hopcroft-Karp maximum matching between every node and the nodes it
+/// reaches, with iterative searches so deep chains cannot overflow the stack.
+fn max_matching(reach: &[Vec<u64>]) -> usize {
+    let n = reach.len();
+    let mut match_left: Vec<Option<usize>> = vec![None; n];
+    let mut match_right: Vec<Option<usize>> = vec![None; n];
+    let mut matching = 0;
+    // a greedy pass matches most nodes of chain-like blocks up front
+    for node in 0..n {
+        let mut cursor = 0;
+        while let Some(target) = next_bit(&reach[node], &mut cursor) {
+            if match_right[target].is_none() {
+                match_left[node] = Some(target);
+                match_right[target] = Some(node);
+                matching += 1;
+                break;
+            }
+        }
+    }
+    loop {
+        // layer the free nodes and the nodes matched to what they reach
+        let mut dist = vec![usize::MAX; n];
+        let mut queue: VecDeque<usize> =
+            (0..n).filter(|node| match_left[*node].is_none()).collect();
+        queue.iter().for_each(|node| dist[*node] = 0);
+        let mut found = false;
+        while let Some(node) = queue.pop_front() {
+            let mut cursor = 0;
+            while let Some(target) = next_bit(&reach[node], &mut cursor) {
+                match match_right[target] {
+                    None => found = true,
+                    Some(other) if dist[other] == usize::MAX => {
+                        dist[other] = dist[node] + 1;
+                        queue.push_back(other);
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        if !found {
+            return matching;
+        }
+        // augment along the layers, resuming each node's scan where it stopped
+        let mut cursors = vec![0; n];
+        for root in 0..n {
+            if match_left[root].is_some() {
+                continue;
+            }
+            let mut path = vec![root];
+            let mut targets = vec![];
+            while let Some(&node) = path.last() {
+                let Some(target) = next_bit(&reach[node], &mut cursors[node]) else {
+                    // dead end for the rest of the phase
+                    dist[node] = usize::MAX;
+                    path.pop();
+                    targets.pop();
+                    continue;
+                };
+                match match_right[target] {
+                    None => {
+                        targets.push(target);
+                        for (node, target) in path.iter().zip(&targets) {
+                            match_left[*node] = Some(*target);
+                            match_right[*target] = Some(*node);
+                        }
+                        matching += 1;
+                        break;
+                    }
+                    Some(other) if dist[other] == dist[node] + 1 => {
+                        targets.push(target);
+                        path.push(other);
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+}
+
+/// The resource an edge is counted under: nonce before storage before code
+/// before balance, so a same-sender edge, which also touches the sender's
+/// balance, counts as a nonce edge only.
+fn edge_resource(dep_type: i16) -> Option<i16> {
+    [DEP_NONCE, DEP_STORAGE, DEP_CODE, DEP_BALANCE]
+        .into_iter()
+        .find(|resource| (dep_type / resource) & 0xf != 0)
+}
+
+fn resource_edge_count(edges: &[DagEdge], resource: i16) -> i64 {
+    edges
+        .iter()
+        .filter(|edge| edge_resource(edge.dep_type) == Some(resource))
+        .count() as i64
+}
+
+/// Computes the parallelism metrics of a DAG whose `nodes` are the
+/// transaction indexes of the block with their gas used. The `pseudo_txs`
+/// among them are left out of the transaction counts.
+pub fn dag_metrics(
+    nodes: &[(i64, u64)],
+    pseudo_txs: &BTreeSet<i64>,
+    edges: &[DagEdge],
+) -> DagMetrics {
+    let mut nodes = nodes.to_vec();
+    nodes.sort_unstable();
+    let dag = Dag::new(&nodes, edges);
+    let counted: Vec<bool> = nodes
+        .iter()
+        .map(|(tx_index, _)| !pseudo_txs.contains(tx_index))
+        .collect();
+    let tx_count = counted.iter().filter(|counted| **counted).count();
+    let levels = dag.levels(&counted);
+    let critical_path_length = levels.iter().copied().max().unwrap_or_default();
+    let speedup = if critical_path_length == 0 {
+        1.0
+    } else {
+        tx_count as f64 / critical_path_length as f64
+    };
+    let total_gas: u64 = dag.gas.iter().sum();
+    let critical_path_gas = dag.gas_finish().into_iter().max().unwrap_or_default();
+    let gas_speedup = if critical_path_gas == 0 {
+        1.0
+    } else {
+        total_gas as f64 / critical_path_gas as f64
+    };
+    DagMetrics {
+        tx_count: tx_count as i64,
+        pseudo_tx_count: (dag.len() - tx_count) as i64,
+        edge_count: dag.deps.iter().map(Vec::len).sum::<usize>() as i64,
+        balance_edge_count: resource_edge_count(edges, DEP_BALANCE),
+        code_edge_count: resource_edge_count(edges, DEP_CODE),
+        storage_edge_count: resource_edge_count(edges, DEP_STORAGE),
+        nonce_edge_count: resource_edge_count(edges, DEP_NONCE),
+        critical_path_length,
+        max_width: dag.max_width() as i64,
+        components: dag.components() as i64,
+        speedup,
+        total_gas: total_gas as i64,
+        critical_path_gas: critical_path_gas as i64,
+        gas_speedup,
+    }
+}
+
+/// Simulates list scheduling of the DAG on each of the given worker counts,
+/// using gas used as the cost of every transaction.
+pub fn simulate_schedules(
+    nodes: &[(i64, u64)],
+    edges: &[DagEdge],
+    workers: &[usize],
+) -> Vec<ScheduleSim> {
+    let mut nodes = nodes.to_vec();
+    nodes.sort_unstable();
+    let dag = Dag::new(&nodes, edges);
+    workers
+        .iter()
+        .map(|workers| dag.schedule(*workers))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parallel_analyzer::HAZARD_RAW;
+
+    fn edge(source: i64, target: i64, dep_type: i16) -> DagEdge {
+        DagEdge {
+            source,
+            target,
+            dep_type,
+            conflicts: vec![],
+        }
+    }
+
+    fn storage_edges(edges: &[(i64, i64)]) -> Vec<DagEdge> {
+        edges
+            .iter()
+            .map(|(source, target)| edge(*source, *target, DEP_STORAGE * HAZARD_RAW))
+            .collect()
+    }
+
+    fn nodes(gas: &[u64]) -> Vec<(i64, u64)> {
+        gas.iter()
+            .enumerate()
+            .map(|(tx_index, gas)| (tx_index as i64, *gas))
+            .collect()
+    }
+
+    #[test]
+    fn diamond() {
+        // 1 and 2 depend on 0, 3 depends on 1 and 2
+        let mut edges = storage_edges(&[(1, 0), (2, 0), (3, 1)]);
+        edges.push(edge(3, 2, (DEP_BALANCE + DEP_STORAGE) * HAZARD_RAW));
+
+        assert_eq!(
+            dag_metrics(&nodes(&[10, 20, 30, 40]), &BTreeSet::new(), &edges),
+            DagMetrics {
+                tx_count: 4,
+                pseudo_tx_count: 0,
+                edge_count: 4,
+                // 3 -> 2 counts as a storage edge only
+                balance_edge_count: 0,
+                code_edge_count: 0,
+                storage_edge_count: 4,
+                nonce_edge_count: 0,
+                critical_path_length: 3,
+                max_width: 2,
+                components: 1,
+                speedup: 4.0 / 3.0,
+                total_gas: 100,
+                critical_path_gas: 80,
+                gas_speedup: 1.25,
+            }
+        );
+    }
+
+    #[test]
+    fn edges_count_under_one_resource() {
+        let edges = vec![
+            // same sender, which also touches the sender's balance
+            edge(1, 0, (DEP_NONCE + DEP_BALANCE) * HAZARD_RAW),
+            edge(2, 0, (DEP_CODE + DEP_BALANCE) * HAZARD_RAW),
+            edge(3, 0, DEP_BALANCE * HAZARD_RAW),
+            edge(3, 2, (DEP_NONCE + DEP_STORAGE) * HAZARD_RAW),
+        ];
+        let metrics = dag_metrics(&nodes(&[10, 10, 10, 10]), &BTreeSet::new(), &edges);
+
+        assert_eq!(
+            (
+                metrics.nonce_edge_count,
+                metrics.storage_edge_count,
+                metrics.code_edge_count,
+                metrics.balance_edge_count,
+            ),
+            (2, 0, 1, 1)
+        );
+        assert_eq!(
+            metrics.nonce_edge_count
+                + metrics.storage_edge_count
+                + metrics.code_edge_count
+                + metrics.balance_edge_count,
+            metrics.edge_count
+        );
+    }
+
+    #[test]
+    fn chain() {
+        let metrics = dag_metrics(
+            &nodes(&[10, 10, 10, 10]),
+            &BTreeSet::new(),
+            &storage_edges(&[(1, 0), (2, 1), (3, 2)]),
+        );
+
+        assert_eq!(
+            (
+                metrics.critical_path_length,
+                metrics.max_width,
+                metrics.components,
+                metrics.speedup,
+                metrics.critical_path_gas,
+                metrics.gas_speedup,
+            ),
+            (4, 1, 1, 1.0, 40, 1.0)
+        );
+    }
+
+    #[test]
+    fn disjoint_components() {
+        // {0, 1}, {2, 3} and {4}
+        let metrics = dag_metrics(
+            &nodes(&[10, 10, 10, 30, 10]),
+            &BTreeSet::new(),
+            &storage_edges(&[(1, 0), (3, 2)]),
+        );
+
+        assert_eq!(
+            (
+                metrics.critical_path_length,
+                metrics.max_width,
+                metrics.components,
+                metrics.speedup,
+                metrics.critical_path_gas,
+            ),
+            (2, 3, 3, 2.5, 40)
+        );
+    }
+
+    #[test]
+    fn antichain() {
+        let metrics = dag_metrics(&nodes(&[10, 10, 10, 10, 10]), &BTreeSet::new(), &[]);
+
+        assert_eq!(
+            (
+                metrics.edge_count,
+                metrics.critical_path_length,
+                metrics.max_width,
+                metrics.components,
+                metrics.speedup,
+                metrics.gas_speedup,
+            ),
+            (0, 1, 5, 5, 5.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn max_width_counts_transitive_dependencies() {
+        // 2 reaches 0 through 1, so {0, 2} is not an antichain, {1, 3} is
+        let metrics = dag_metrics(
+            &nodes(&[10, 10, 10, 10]),
+            &BTreeSet::new(),
+            &storage_edges(&[(1, 0), (2, 1), (3, 0)]),
+        );
+
+        assert_eq!(metrics.max_width, 2);
+
+        // the greedy pass matches 2 with 0 and leaves 3 to an augmenting path
+        let metrics = dag_metrics(
+            &nodes(&[10, 10, 10, 10]),
+            &BTreeSet::new(),
+            &storage_edges(&[(2, 0), (2, 1), (3, 0)]),
+        );
+
+        assert_eq!(metrics.max_width, 2);
+    }
+
+    #[test]
+    fn pseudo_transactions_are_not_counted() {
+        // system calls at -1 before 0 and 1, withdrawals at 2 after 1
+        let metrics = dag_metrics(
+            &[(-1, 0), (0, 10), (1, 10), (2, 0)],
+            &BTreeSet::from([-1, 2]),
+            &storage_edges(&[(0, -1), (1, 0), (2, 1)]),
+        );
+
+        assert_eq!(
+            (
+                metrics.tx_count,
+                metrics.pseudo_tx_count,
+                metrics.edge_count,
+                metrics.critical_path_length,
+                metrics.speedup,
+            ),
+            (2, 2, 3, 2, 1.0)
+        );
+    }
+
+    #[test]
+    fn large_blocks() {
+        let gas = vec![21_000; 2_000];
+        let chain: Vec<(i64, i64)> = (1..2_000).map(|tx| (tx, tx - 1)).collect();
+        let metrics = dag_metrics(&nodes(&gas), &BTreeSet::new(), &storage_edges(&chain));
+        assert_eq!(
+            (
+                metrics.critical_path_length,
+                metrics.max_width,
+                metrics.components
+            ),
+            (2_000, 1, 1)
+        );
+
+        // 40 interleaved chains of 50 transactions
+        let chains: Vec<(i64, i64)> = (40..2_000).map(|tx| (tx, tx - 40)).collect();
+        let metrics = dag_metrics(&nodes(&gas), &BTreeSet::new(), &storage_edges(&chains));
+        assert_eq!(
+            (
+                metrics.critical_path_length,
+                metrics.max_width,
+                metrics.components
+            ),
+            (50, 40, 40)
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::time::OffsetDateTime};
+
+use super::DB;
+
+pub const RANGE_PENDING: &str = "pending";
+pub const RANGE_RUNNING: &str = "running";
+pub const RANGE_DONE: &str = "done";
+
+#[derive(FromRow, Debug, Clone, Deserialize, Serialize)]
+pub struct AnalysisRange {
+    pub id: Option<i64>,
+    pub chain_id: i64,
+    pub start_block: i64,
+    pub end_block: i64,
+    /// `BackfillDirection` the range is walked in
+    pub direction: String,
+    /// next block to analyze, past the range once it is done
+    pub next_block: i64,
+    /// `RANGE_PENDING`, `RANGE_RUNNING` or `RANGE_DONE`
+    pub status: String,
+    /// worker that last claimed the range
+    pub worker: Option<String>,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[allow(unused)]
+pub trait AnalysisRangeDB {
+    async fn insert_analysis_ranges(&self, ranges: &[AnalysisRange]) -> Result<(), sqlx::Error>;
+    async fn claim_analysis_range(
+        &self,
+        chain_id: i64,
+        worker: &str,
+        newest_first: bool,
+        stale_range_secs: i64,
+    ) -> Result<Option<AnalysisRange>, sqlx::Error>;
+    async fn update_analysis_range(
+        &self,
+        id: i64,
+        next_block: i64,
+        status: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_analysis_ranges_by_chainid(
+        &self,
+        chain_id: i64,
+    ) -> Result<Vec<AnalysisRange>, sqlx::Error>;
+}
+
+impl AnalysisRangeDB for DB {
+    /// Skips ranges whose start block is already recorded.
+    async fn insert_analysis_ranges(&self, ranges: &[AnalysisRange]) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        for range in ranges {
+            sqlx::query(
+                r#"
+                INSERT INTO analysis_ranges (chain_id, start_block, end_block, direction, next_block, status)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (chain_id, start_block) DO NOTHING
+                "#,
+            )
+            .bind(range.chain_id)
+            .bind(range.start_block)
+            .bind(range.end_block)
+            .bind(range.direction.clone())
+            .bind(range.next_block)
+            .bind(range.status.clone())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Marks the newest or oldest pending range, or a running range not
+    /// updated for `stale_range_secs`, as running for `worker`. Ranges locked
+    /// by a concurrent claim are skipped.
+    async fn claim_analysis_range(
+        &self,
+        chain_id: i64,
+        worker: &str,
+        newest_first: bool,
+        stale_range_secs: i64,
+    ) -> Result<Option<AnalysisRange>, sqlx::Error> {
+        let order = if newest_first { "DESC" } else { "ASC" };
+        let range = sqlx::query_as::<_, AnalysisRange>(&format!(
+            r#"
+            UPDATE analysis_ranges SET status = 'running', worker = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE id = (
+                SELECT id FROM analysis_ranges
+                WHERE chain_id = $1 AND (
+                    status = 'pending'
+                    OR (status = 'running' AND updated_at < CURRENT_TIMESTAMP - make_interval(secs => $3))
+                )
+                ORDER BY start_block {order}
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#
+        ))
+        .bind(chain_id)
+        .bind(worker)
+        .bind(stale_range_secs as f64)
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(range)
+    }
+
+    async fn update_analysis_range(
+        &self,
+        id: i64,
+        next_block: i64,
+        status: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE analysis_ranges SET next_block = $1, status = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $3
+            "#,
+        )
+        .bind(next_block)
+        .bind(status)
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_analysis_ranges_by_chainid(
+        &self,
+        chain_id: i64,
+    ) -> Result<Vec<AnalysisRange>, sqlx::Error> {
+        let ranges = sqlx::query_as::<_, AnalysisRange>(
+            r#"
+            SELECT * FROM analysis_ranges WHERE chain_id = $1 ORDER BY start_block
+            "#,
+        )
+        .bind(chain_id)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(ranges)
+    }
+}
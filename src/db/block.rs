@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, types::time::OffsetDateTime};
+use sqlx::{FromRow, PgConnection, PgExecutor, types::time::OffsetDateTime};
 
-use super::DB;
+use super::{
+    DB,
+    block_parallelism_stats::{BlockParallelismStats, upsert_block_parallelism_stats},
+    block_schedule_sim::{
+        BlockScheduleSim, delete_block_schedule_sims_by_block_number, upsert_block_schedule_sim,
+    },
+    transaction_dag::{
+        TransactionDag, delete_transaction_dags_by_block_number, insert_transaction_dag,
+    },
+};
 
 #[derive(FromRow, Debug, Deserialize, Serialize)]
 pub struct Block {
@@ -14,7 +23,14 @@ pub struct Block {
     pub base_fee_per_gas: i64,
     pub blob_gas_used: i64,
     pub excess_blob_gas: i64,
+    /// version of the dependency algorithm that produced this block's DAG, 0
+    /// if not analyzed yet
+    pub dag_version: i16,
+    /// beneficiary of the block, none for blocks stored before it was recorded
+    pub fee_recipient: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
     pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
     pub updated_at: Option<OffsetDateTime>,
 }
 
@@ -23,15 +39,34 @@ pub trait BlockDB {
     async fn insert_block(&self, block: &Block) -> Result<(), sqlx::Error>;
     async fn get_block_by_number(&self, block_number: i64) -> Result<Option<Block>, sqlx::Error>;
     async fn get_block_by_hash(&self, block_hash: &str) -> Result<Option<Block>, sqlx::Error>;
+    async fn get_highest_block_up_to(
+        &self,
+        block_number: i64,
+    ) -> Result<Option<Block>, sqlx::Error>;
+    async fn update_block_dag_version(
+        &self,
+        block_number: i64,
+        dag_version: i16,
+    ) -> Result<(), sqlx::Error>;
+    async fn delete_blocks_after(&self, block_number: i64) -> Result<u64, sqlx::Error>;
+    async fn replace_block_analysis(
+        &self,
+        block_number: i64,
+        dags: &[TransactionDag],
+        stats: &BlockParallelismStats,
+        sims: &[BlockScheduleSim],
+        dag_version: i16,
+    ) -> Result<(), sqlx::Error>;
 }
 
 impl BlockDB for DB {
     async fn insert_block(&self, block: &Block) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO blocks (parent_hash, block_hash, block_number, gas_used, gas_limit, block_timestamp, base_fee_per_gas, blob_gas_used, excess_blob_gas)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-            ON CONFLICT (block_hash) DO NOTHING
+            INSERT INTO blocks (parent_hash, block_hash, block_number, gas_used, gas_limit, block_timestamp, base_fee_per_gas, blob_gas_used, excess_blob_gas, fee_recipient)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (block_hash) DO UPDATE SET
+                fee_recipient = COALESCE(blocks.fee_recipient, EXCLUDED.fee_recipient)
             "#,
           )
           .bind(block.parent_hash.clone())
@@ -43,6 +78,7 @@ impl BlockDB for DB {
           .bind(block.base_fee_per_gas)
           .bind(block.blob_gas_used)
           .bind(block.excess_blob_gas)
+          .bind(block.fee_recipient.clone())
           .execute(&self.db)
           .await?;
         Ok(())
@@ -69,4 +105,108 @@ impl BlockDB for DB {
         .await?;
         Ok(block)
     }
+    /// The stored block with the highest number not above `block_number`.
+    async fn get_highest_block_up_to(
+        &self,
+        block_number: i64,
+    ) -> Result<Option<Block>, sqlx::Error> {
+        let block = sqlx::query_as::<_, Block>(
+            r#"
+            SELECT * FROM blocks WHERE block_number <= $1 ORDER BY block_number DESC LIMIT 1
+            "#,
+        )
+        .bind(block_number)
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(block)
+    }
+    async fn update_block_dag_version(
+        &self,
+        block_number: i64,
+        dag_version: i16,
+    ) -> Result<(), sqlx::Error> {
+        update_block_dag_version(&self.db, block_number, dag_version).await
+    }
+
+    /// Deletes every block above `block_number` along with its transactions,
+    /// DAG, stats and simulations. Returns the number of deleted blocks.
+    async fn delete_blocks_after(&self, block_number: i64) -> Result<u64, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        let deleted = delete_blocks_after(&mut tx, block_number).await?;
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Replaces the DAG, stats and schedule simulations of a block and sets
+    /// its `dag_version` in one transaction, so a failure leaves the previous
+    /// analysis and version in place.
+    async fn replace_block_analysis(
+        &self,
+        block_number: i64,
+        dags: &[TransactionDag],
+        stats: &BlockParallelismStats,
+        sims: &[BlockScheduleSim],
+        dag_version: i16,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        delete_transaction_dags_by_block_number(&mut *tx, block_number).await?;
+        for dag in dags {
+            insert_transaction_dag(&mut *tx, dag).await?;
+        }
+        upsert_block_parallelism_stats(&mut *tx, stats).await?;
+        // worker counts dropped from `simulated_workers` must not survive
+        delete_block_schedule_sims_by_block_number(&mut *tx, block_number).await?;
+        for sim in sims {
+            upsert_block_schedule_sim(&mut *tx, sim).await?;
+        }
+        update_block_dag_version(&mut *tx, block_number, dag_version).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// `BlockDB::delete_blocks_after` within the caller's transaction.
+pub(super) async fn delete_blocks_after(
+    conn: &mut PgConnection,
+    block_number: i64,
+) -> Result<u64, sqlx::Error> {
+    for table in [
+        "transaction_dags",
+        "transactions",
+        "transaction_state_sets",
+        "block_parallelism_stats",
+        "block_schedule_sims",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE block_number > $1"))
+            .bind(block_number)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let deleted = sqlx::query(
+        r#"
+        DELETE FROM blocks WHERE block_number > $1
+        "#,
+    )
+    .bind(block_number)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(deleted)
+}
+
+pub(super) async fn update_block_dag_version<'e>(
+    executor: impl PgExecutor<'e>,
+    block_number: i64,
+    dag_version: i16,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE blocks SET dag_version = $1, updated_at = CURRENT_TIMESTAMP WHERE block_number = $2
+        "#,
+    )
+    .bind(dag_version)
+    .bind(block_number)
+    .execute(executor)
+    .await?;
+    Ok(())
 }
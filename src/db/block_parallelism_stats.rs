@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    FromRow, PgExecutor,
+    types::{Json, time::OffsetDateTime},
+};
+
+use super::DB;
+
+#[derive(FromRow, Debug, Deserialize, Serialize)]
+pub struct BlockParallelismStats {
+    pub block_number: i64,
+    pub tx_count: i64,
+    /// pre-block system calls and withdrawals in the DAG, not in `tx_count`
+    pub pseudo_tx_count: i64,
+    pub edge_count: i64,
+    pub balance_edge_count: i64,
+    pub code_edge_count: i64,
+    pub storage_edge_count: i64,
+    pub nonce_edge_count: i64,
+    pub critical_path_length: i64,
+    pub max_width: i64,
+    pub components: i64,
+    pub speedup: f64,
+    pub total_gas: i64,
+    pub critical_path_gas: i64,
+    pub gas_speedup: f64,
+    /// addresses whose balance was left out of dependency detection
+    pub balance_exclusions: Json<Vec<String>>,
+    /// metrics of the DAG built without exclusions
+    pub naive_edge_count: i64,
+    pub naive_critical_path_length: i64,
+    pub naive_speedup: f64,
+    pub naive_critical_path_gas: i64,
+    pub naive_gas_speedup: f64,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[allow(unused)]
+pub trait BlockParallelismStatsDB {
+    async fn upsert_block_parallelism_stats(
+        &self,
+        stats: &BlockParallelismStats,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_block_parallelism_stats_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Option<BlockParallelismStats>, sqlx::Error>;
+    async fn get_block_parallelism_stats_by_range(
+        &self,
+        start_block: i64,
+        end_block: i64,
+    ) -> Result<Vec<BlockParallelismStats>, sqlx::Error>;
+}
+
+impl BlockParallelismStatsDB for DB {
+    async fn upsert_block_parallelism_stats(
+        &self,
+        stats: &BlockParallelismStats,
+    ) -> Result<(), sqlx::Error> {
+        upsert_block_parallelism_stats(&self.db, stats).await
+    }
+
+    async fn get_block_parallelism_stats_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Option<BlockParallelismStats>, sqlx::Error> {
+        let stats = sqlx::query_as::<_, BlockParallelismStats>(
+            r#"
+            SELECT * FROM block_parallelism_stats WHERE block_number = $1
+            "#,
+        )
+        .bind(block_number)
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(stats)
+    }
+
+    async fn get_block_parallelism_stats_by_range(
+        &self,
+        start_block: i64,
+        end_block: i64,
+    ) -> Result<Vec<BlockParallelismStats>, sqlx::Error> {
+        let stats = sqlx::query_as::<_, BlockParallelismStats>(
+            r#"
+            SELECT * FROM block_parallelism_stats
+            WHERE block_number BETWEEN $1 AND $2
+            ORDER BY block_number
+            "#,
+        )
+        .bind(start_block)
+        .bind(end_block)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(stats)
+    }
+}
+
+pub(super) async fn upsert_block_parallelism_stats<'e>(
+    executor: impl PgExecutor<'e>,
+    stats: &BlockParallelismStats,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO block_parallelism_stats (block_number, tx_count, edge_count, critical_path_length, max_width, components, speedup, total_gas, critical_path_gas, gas_speedup, balance_edge_count, code_edge_count, storage_edge_count, nonce_edge_count, balance_exclusions, naive_edge_count, naive_critical_path_length, naive_speedup, naive_critical_path_gas, naive_gas_speedup, pseudo_tx_count)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,$18,$19,$20,$21)
+        ON CONFLICT (block_number) DO UPDATE SET
+            tx_count = EXCLUDED.tx_count,
+            edge_count = EXCLUDED.edge_count,
+            critical_path_length = EXCLUDED.critical_path_length,
+            max_width = EXCLUDED.max_width,
+            components = EXCLUDED.components,
+            speedup = EXCLUDED.speedup,
+            total_gas = EXCLUDED.total_gas,
+            critical_path_gas = EXCLUDED.critical_path_gas,
+            gas_speedup = EXCLUDED.gas_speedup,
+            balance_edge_count = EXCLUDED.balance_edge_count,
+            code_edge_count = EXCLUDED.code_edge_count,
+            storage_edge_count = EXCLUDED.storage_edge_count,
+            nonce_edge_count = EXCLUDED.nonce_edge_count,
+            balance_exclusions = EXCLUDED.balance_exclusions,
+            naive_edge_count = EXCLUDED.naive_edge_count,
+            naive_critical_path_length = EXCLUDED.naive_critical_path_length,
+            naive_speedup = EXCLUDED.naive_speedup,
+            naive_critical_path_gas = EXCLUDED.naive_critical_path_gas,
+            naive_gas_speedup = EXCLUDED.naive_gas_speedup,
+            pseudo_tx_count = EXCLUDED.pseudo_tx_count,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(stats.block_number)
+    .bind(stats.tx_count)
+    .bind(stats.edge_count)
+    .bind(stats.critical_path_length)
+    .bind(stats.max_width)
+    .bind(stats.components)
+    .bind(stats.speedup)
+    .bind(stats.total_gas)
+    .bind(stats.critical_path_gas)
+    .bind(stats.gas_speedup)
+    .bind(stats.balance_edge_count)
+    .bind(stats.code_edge_count)
+    .bind(stats.storage_edge_count)
+    .bind(stats.nonce_edge_count)
+    .bind(&stats.balance_exclusions)
+    .bind(stats.naive_edge_count)
+    .bind(stats.naive_critical_path_length)
+    .bind(stats.naive_speedup)
+    .bind(stats.naive_critical_path_gas)
+    .bind(stats.naive_gas_speedup)
+    .bind(stats.pseudo_tx_count)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, types::time::OffsetDateTime};
+
+use super::DB;
+
+#[derive(FromRow, Debug, Deserialize, Serialize)]
+pub struct BlockScheduleSim {
+    pub block_number: i64,
+    pub workers: i64,
+    pub makespan_gas: i64,
+    pub utilization: f64,
+    pub speedup: f64,
+    /// Block-STM replay with `workers` threads
+    pub stm_makespan_gas: i64,
+    pub stm_aborts: i64,
+    pub stm_wasted_gas: i64,
+    pub stm_speedup: f64,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+/// Simulated schedules of a block range summed per worker count.
+#[derive(FromRow, Debug, Deserialize, Serialize)]
+pub struct ScheduleSimTotals {
+    pub workers: i64,
+    pub block_count: i64,
+    pub total_gas: i64,
+    pub makespan_gas: i64,
+    pub stm_makespan_gas: i64,
+    pub stm_aborts: i64,
+    pub stm_wasted_gas: i64,
+}
+
+#[allow(unused)]
+pub trait BlockScheduleSimDB {
+    async fn upsert_block_schedule_sim(&self, sim: &BlockScheduleSim) -> Result<(), sqlx::Error>;
+    async fn delete_block_schedule_sims_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_block_schedule_sims_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Vec<BlockScheduleSim>, sqlx::Error>;
+    async fn get_schedule_sim_totals_by_range(
+        &self,
+        start_block: i64,
+        end_block: i64,
+    ) -> Result<Vec<ScheduleSimTotals>, sqlx::Error>;
+}
+
+impl BlockScheduleSimDB for DB {
+    async fn upsert_block_schedule_sim(&self, sim: &BlockScheduleSim) -> Result<(), sqlx::Error> {
+        upsert_block_schedule_sim(&self.db, sim).await
+    }
+
+    async fn delete_block_schedule_sims_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<(), sqlx::Error> {
+        delete_block_schedule_sims_by_block_number(&self.db, block_number).await
+    }
+
+    async fn get_block_schedule_sims_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Vec<BlockScheduleSim>, sqlx::Error> {
+        let sims = sqlx::query_as::<_, BlockScheduleSim>(
+            r#"
+            SELECT * FROM block_schedule_sims WHERE block_number = $1 ORDER BY workers
+            "#,
+        )
+        .bind(block_number)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(sims)
+    }
+
+    async fn get_schedule_sim_totals_by_range(
+        &self,
+        start_block: i64,
+        end_block: i64,
+    ) -> Result<Vec<ScheduleSimTotals>, sqlx::Error> {
+        let totals = sqlx::query_as::<_, ScheduleSimTotals>(
+            r#"
+            SELECT s.workers,
+                COUNT(*) AS block_count,
+                SUM(p.total_gas)::BIGINT AS total_gas,
+                SUM(s.makespan_gas)::BIGINT AS makespan_gas,
+                SUM(s.stm_makespan_gas)::BIGINT AS stm_makespan_gas,
+                SUM(s.stm_aborts)::BIGINT AS stm_aborts,
+                SUM(s.stm_wasted_gas)::BIGINT AS stm_wasted_gas
+            FROM block_schedule_sims s
+            JOIN block_parallelism_stats p ON p.block_number = s.block_number
+            WHERE s.block_number BETWEEN $1 AND $2
+            GROUP BY s.workers
+            ORDER BY s.workers
+            "#,
+        )
+        .bind(start_block)
+        .bind(end_block)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(totals)
+    }
+}
+
+pub(super) async fn delete_block_schedule_sims_by_block_number<'e>(
+    executor: impl PgExecutor<'e>,
+    block_number: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM block_schedule_sims WHERE block_number = $1
+        "#,
+    )
+    .bind(block_number)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn upsert_block_schedule_sim<'e>(
+    executor: impl PgExecutor<'e>,
+    sim: &BlockScheduleSim,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO block_schedule_sims (block_number, workers, makespan_gas, utilization, speedup, stm_makespan_gas, stm_aborts, stm_wasted_gas, stm_speedup)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
+        ON CONFLICT (block_number, workers) DO UPDATE SET
+            makespan_gas = EXCLUDED.makespan_gas,
+            utilization = EXCLUDED.utilization,
+            speedup = EXCLUDED.speedup,
+            stm_makespan_gas = EXCLUDED.stm_makespan_gas,
+            stm_aborts = EXCLUDED.stm_aborts,
+            stm_wasted_gas = EXCLUDED.stm_wasted_gas,
+            stm_speedup = EXCLUDED.stm_speedup,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(sim.block_number)
+    .bind(sim.workers)
+    .bind(sim.makespan_gas)
+    .bind(sim.utilization)
+    .bind(sim.speedup)
+    .bind(sim.stm_makespan_gas)
+    .bind(sim.stm_aborts)
+    .bind(sim.stm_wasted_gas)
+    .bind(sim.stm_speedup)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::time::OffsetDateTime};
+
+use super::DB;
+
+#[derive(FromRow, Debug, Deserialize, Serialize)]
+pub struct FailedBlock {
+    pub chain_id: i64,
+    pub block_number: i64,
+    /// `ErrorClass` of the last failure
+    pub error_class: String,
+    pub error: String,
+    /// attempts across the analyzer loop and every retry
+    pub attempts: i64,
+    pub created_at: Option<OffsetDateTime>,
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+#[allow(unused)]
+pub trait FailedBlockDB {
+    async fn upsert_failed_block(&self, failed_block: &FailedBlock) -> Result<(), sqlx::Error>;
+    async fn get_failed_blocks_by_chainid(
+        &self,
+        chain_id: i64,
+        limit: i64,
+    ) -> Result<Vec<FailedBlock>, sqlx::Error>;
+    async fn delete_failed_block(
+        &self,
+        chain_id: i64,
+        block_number: i64,
+    ) -> Result<(), sqlx::Error>;
+}
+
+impl FailedBlockDB for DB {
+    /// Adds `attempts` to the attempts of an already failed block.
+    async fn upsert_failed_block(&self, failed_block: &FailedBlock) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO failed_blocks (chain_id, block_number, error_class, error, attempts)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (chain_id, block_number) DO UPDATE SET
+                error_class = EXCLUDED.error_class,
+                error = EXCLUDED.error,
+                attempts = failed_blocks.attempts + EXCLUDED.attempts,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(failed_block.chain_id)
+        .bind(failed_block.block_number)
+        .bind(failed_block.error_class.clone())
+        .bind(failed_block.error.clone())
+        .bind(failed_block.attempts)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_failed_blocks_by_chainid(
+        &self,
+        chain_id: i64,
+        limit: i64,
+    ) -> Result<Vec<FailedBlock>, sqlx::Error> {
+        let failed_blocks = sqlx::query_as::<_, FailedBlock>(
+            r#"
+            SELECT * FROM failed_blocks WHERE chain_id = $1 ORDER BY block_number LIMIT $2
+            "#,
+        )
+        .bind(chain_id)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(failed_blocks)
+    }
+
+    async fn delete_failed_block(
+        &self,
+        chain_id: i64,
+        block_number: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM failed_blocks WHERE chain_id = $1 AND block_number = $2
+            "#,
+        )
+        .bind(chain_id)
+        .bind(block_number)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
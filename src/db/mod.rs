@@ -1,7 +1,13 @@
+pub mod analysis_range;
 pub mod block;
+pub mod block_parallelism_stats;
+pub mod block_schedule_sim;
 mod database;
+pub mod failed_block;
 pub mod parallel_analyzer_state;
+pub mod reorg;
 pub mod transaction;
 pub mod transaction_dag;
+pub mod transaction_state_set;
 
 pub use database::DB;
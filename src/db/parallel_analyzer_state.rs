@@ -9,6 +9,8 @@ pub struct ParallelAnalyzerState {
     pub chain_id: i64,
     pub start_block: i64,
     pub latest_analyzed_block: i64,
+    /// `analysis_horizon` that `latest_block` was read from
+    pub horizon: String,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
@@ -36,8 +38,8 @@ impl ParallelAnalyzerStateDB for DB {
     ) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO parallel_analyzer_state (latest_block, chain_id, start_block, latest_analyzed_block)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO parallel_analyzer_state (latest_block, chain_id, start_block, latest_analyzed_block, horizon)
+            VALUES ($1, $2, $3, $4, $5)
             ON CONFLICT (chain_id) DO NOTHING
             "#,
         )
@@ -45,6 +47,7 @@ impl ParallelAnalyzerStateDB for DB {
         .bind(parallel_analyzer_state.chain_id)
         .bind(parallel_analyzer_state.start_block)
         .bind(parallel_analyzer_state.latest_analyzed_block)
+        .bind(parallel_analyzer_state.horizon.clone())
         .execute(&self.db)
         .await?;
         Ok(())
@@ -71,13 +74,14 @@ impl ParallelAnalyzerStateDB for DB {
         sqlx::query(
             r#"
             UPDATE parallel_analyzer_state
-            SET latest_block = $1, start_block = $2, latest_analyzed_block = $3
-            WHERE chain_id = $4
+            SET latest_block = $1, start_block = $2, latest_analyzed_block = $3, horizon = $4
+            WHERE chain_id = $5
             "#,
         )
         .bind(parallel_analyzer_state.latest_block)
         .bind(parallel_analyzer_state.start_block)
         .bind(parallel_analyzer_state.latest_analyzed_block)
+        .bind(parallel_analyzer_state.horizon.clone())
         .bind(parallel_analyzer_state.chain_id)
         .execute(&self.db)
         .await?;
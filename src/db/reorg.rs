@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::time::OffsetDateTime};
+
+use super::{DB, block::delete_blocks_after};
+
+#[derive(FromRow, Debug, Deserialize, Serialize)]
+pub struct Reorg {
+    pub id: Option<i64>,
+    pub chain_id: i64,
+    /// last block shared by the orphaned and the canonical branch
+    pub fork_block: i64,
+    /// stored blocks deleted above `fork_block`
+    pub orphaned_blocks: i64,
+    /// stored and canonical hash of block `fork_block + 1`
+    pub old_block_hash: String,
+    pub new_block_hash: String,
+    pub created_at: Option<OffsetDateTime>,
+}
+
+#[allow(unused)]
+pub trait ReorgDB {
+    async fn insert_reorg(&self, reorg: &Reorg) -> Result<(), sqlx::Error>;
+    async fn roll_back_reorg(&self, reorg: &Reorg) -> Result<u64, sqlx::Error>;
+    async fn get_reorgs_by_chainid(
+        &self,
+        chain_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Reorg>, sqlx::Error>;
+}
+
+impl ReorgDB for DB {
+    async fn insert_reorg(&self, reorg: &Reorg) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO reorgs (chain_id, fork_block, orphaned_blocks, old_block_hash, new_block_hash)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(reorg.chain_id)
+        .bind(reorg.fork_block)
+        .bind(reorg.orphaned_blocks)
+        .bind(reorg.old_block_hash.clone())
+        .bind(reorg.new_block_hash.clone())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes every block above `reorg.fork_block`, records the reorg with
+    /// the number of deleted blocks as `orphaned_blocks` and moves the
+    /// chain's `latest_analyzed_block` back to the fork block, all in one
+    /// transaction. Returns the number of deleted blocks.
+    async fn roll_back_reorg(&self, reorg: &Reorg) -> Result<u64, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        let orphaned_blocks = delete_blocks_after(&mut tx, reorg.fork_block).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO reorgs (chain_id, fork_block, orphaned_blocks, old_block_hash, new_block_hash)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(reorg.chain_id)
+        .bind(reorg.fork_block)
+        .bind(orphaned_blocks as i64)
+        .bind(reorg.old_block_hash.clone())
+        .bind(reorg.new_block_hash.clone())
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE parallel_analyzer_state
+            SET latest_analyzed_block = LEAST(latest_analyzed_block, $1), updated_at = CURRENT_TIMESTAMP
+            WHERE chain_id = $2
+            "#,
+        )
+        .bind(reorg.fork_block)
+        .bind(reorg.chain_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(orphaned_blocks)
+    }
+
+    async fn get_reorgs_by_chainid(
+        &self,
+        chain_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Reorg>, sqlx::Error> {
+        let reorgs = sqlx::query_as::<_, Reorg>(
+            r#"
+            SELECT * FROM reorgs WHERE chain_id = $1 ORDER BY id DESC LIMIT $2
+            "#,
+        )
+        .bind(chain_id)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(reorgs)
+    }
+}
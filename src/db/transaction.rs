@@ -19,6 +19,14 @@ pub struct Transaction {
     pub input: String,
     pub nonce: i64,
     pub tx_type: i16,
+    /// gas used according to the receipt
+    pub gas_used: i64,
+    /// L1 source hash of a deposit transaction
+    pub source_hash: Option<String>,
+    /// value minted on L2 by a deposit transaction
+    pub mint: Option<String>,
+    /// EIP-7702 authorizations of a set-code transaction
+    pub authorization_count: i64,
     pub created_at: Option<OffsetDateTime>,
     pub updated_at: Option<OffsetDateTime>,
 }
@@ -38,9 +46,14 @@ impl TransactionDB for DB {
     async fn insert_transaction(&self, transaction: &Transaction) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO transactions (block_number, tx_index, tx_hash, tx_from, tx_to, gas_price, max_fee_per_gas, max_priority_fee_per_gas, max_fee_per_blob_gas, gas, tx_value, input, nonce, tx_type)
-            VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14)
-            ON CONFLICT (tx_hash) DO NOTHING
+            INSERT INTO transactions (block_number, tx_index, tx_hash, tx_from, tx_to, gas_price, max_fee_per_gas, max_priority_fee_per_gas, max_fee_per_blob_gas, gas, tx_value, input, nonce, tx_type, gas_used, source_hash, mint, authorization_count)
+            VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,$18)
+            ON CONFLICT (tx_hash) DO UPDATE SET
+                gas_used = EXCLUDED.gas_used,
+                source_hash = EXCLUDED.source_hash,
+                mint = EXCLUDED.mint,
+                authorization_count = EXCLUDED.authorization_count,
+                updated_at = CURRENT_TIMESTAMP
             "#,
             )
             .bind(transaction.block_number)
@@ -57,6 +70,10 @@ impl TransactionDB for DB {
             .bind(transaction.input.clone())
             .bind(transaction.nonce)
             .bind(transaction.tx_type)
+            .bind(transaction.gas_used)
+            .bind(transaction.source_hash.clone())
+            .bind(transaction.mint.clone())
+            .bind(transaction.authorization_count)
             .execute(&self.db)
             .await?;
         Ok(())
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, types::time::OffsetDateTime};
+use sqlx::{
+    FromRow, PgExecutor,
+    types::{Json, time::OffsetDateTime},
+};
 use tracing::debug;
 
 use super::DB;
@@ -9,12 +12,31 @@ pub struct TransactionDag {
     pub block_number: i64,
     pub source_tx: i64,
     pub target_tx: i64,
-    /// 0x1: balance, 0x10: code, 0x100: storage
+    /// one nibble per resource (0x1: balance, 0x10: code, 0x100: storage,
+    /// 0x1000: nonce), each holding hazard bits (0x1: RAW, 0x2: WAW, 0x4: WAR)
     pub dep_type: i16,
+    /// conflicting keys behind the edge, none for edges stored before they
+    /// were recorded
+    pub reasons: Option<Json<Vec<DagReason>>>,
+    #[serde(with = "time::serde::rfc3339::option")]
     pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
     pub updated_at: Option<OffsetDateTime>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DagReason {
+    /// "balance", "code", "storage" or "nonce"
+    pub resource: String,
+    /// "raw", "waw" or "war"
+    pub hazard: String,
+    pub address: String,
+    /// storage slot, for storage conflicts
+    pub slot: Option<String>,
+    /// what wrote the key, when it is not plain execution
+    pub detail: Option<String>,
+}
+
 #[allow(unused)]
 pub trait TransactionDagDB {
     async fn insert_transaction_dag(
@@ -25,6 +47,12 @@ pub trait TransactionDagDB {
         &self,
         block_number: i64,
     ) -> Result<Vec<TransactionDag>, sqlx::Error>;
+    async fn get_transaction_dag(
+        &self,
+        block_number: i64,
+        source_tx: i64,
+        target_tx: i64,
+    ) -> Result<Option<TransactionDag>, sqlx::Error>;
     async fn delete_transaction_dags_by_block_number(
         &self,
         block_number: i64,
@@ -36,20 +64,7 @@ impl TransactionDagDB for DB {
         &self,
         transaction_dag: &TransactionDag,
     ) -> Result<(), sqlx::Error> {
-        debug!("insert transaction_dag {:?}", transaction_dag);
-        sqlx::query(
-            r#"
-            INSERT INTO transaction_dags (block_number, source_tx, target_tx, dep_type)
-            VALUES ($1,$2,$3,$4)
-            "#,
-        )
-        .bind(transaction_dag.block_number)
-        .bind(transaction_dag.source_tx)
-        .bind(transaction_dag.target_tx)
-        .bind(transaction_dag.dep_type)
-        .execute(&self.db)
-        .await?;
-        Ok(())
+        insert_transaction_dag(&self.db, transaction_dag).await
     }
 
     async fn get_transaction_dags_by_block_number(
@@ -67,18 +82,65 @@ impl TransactionDagDB for DB {
         Ok(transaction_dags)
     }
 
-    async fn delete_transaction_dags_by_block_number(
+    async fn get_transaction_dag(
         &self,
         block_number: i64,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
+        source_tx: i64,
+        target_tx: i64,
+    ) -> Result<Option<TransactionDag>, sqlx::Error> {
+        let transaction_dag = sqlx::query_as::<_, TransactionDag>(
             r#"
-            DELETE FROM transaction_dags WHERE block_number = $1
+            SELECT * FROM transaction_dags WHERE block_number = $1 AND source_tx = $2 AND target_tx = $3
             "#,
         )
         .bind(block_number)
-        .execute(&self.db)
+        .bind(source_tx)
+        .bind(target_tx)
+        .fetch_optional(&self.db)
         .await?;
-        Ok(())
+        Ok(transaction_dag)
     }
+
+    async fn delete_transaction_dags_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<(), sqlx::Error> {
+        delete_transaction_dags_by_block_number(&self.db, block_number).await
+    }
+}
+
+pub(super) async fn insert_transaction_dag<'e>(
+    executor: impl PgExecutor<'e>,
+    transaction_dag: &TransactionDag,
+) -> Result<(), sqlx::Error> {
+    debug!("insert transaction_dag {:?}", transaction_dag);
+    sqlx::query(
+        r#"
+        INSERT INTO transaction_dags (block_number, source_tx, target_tx, dep_type, reasons)
+        VALUES ($1,$2,$3,$4,$5)
+        "#,
+    )
+    .bind(transaction_dag.block_number)
+    .bind(transaction_dag.source_tx)
+    .bind(transaction_dag.target_tx)
+    .bind(transaction_dag.dep_type)
+    .bind(&transaction_dag.reasons)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn delete_transaction_dags_by_block_number<'e>(
+    executor: impl PgExecutor<'e>,
+    block_number: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM transaction_dags WHERE block_number = $1
+        "#,
+    )
+    .bind(block_number)
+    .execute(executor)
+    .await?;
+    Ok(())
 }
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use alloy::primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    FromRow,
+    types::{Json, time::OffsetDateTime},
+};
+
+use super::DB;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct TransactionStateSet {
+    pub block_number: i64,
+    /// transaction index, or the index of a pseudo-transaction
+    pub tx_index: i64,
+    pub read_set: Json<StateSetRecord>,
+    pub write_set: Json<StateSetRecord>,
+    /// EIP-7702 authorities whose delegation the transaction applied
+    pub authorities: Json<Vec<Address>>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+/// Keys of one read or write set, sorted so equal sets store equal JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StateSetRecord {
+    pub balance: Vec<Address>,
+    pub code: Vec<Address>,
+    pub nonce: Vec<Address>,
+    /// slots by contract address
+    pub storage: BTreeMap<Address, Vec<B256>>,
+}
+
+#[allow(unused)]
+pub trait TransactionStateSetDB {
+    async fn replace_transaction_state_sets(
+        &self,
+        block_number: i64,
+        state_sets: &[TransactionStateSet],
+    ) -> Result<(), sqlx::Error>;
+    async fn get_transaction_state_sets_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Vec<TransactionStateSet>, sqlx::Error>;
+    async fn get_transaction_state_set(
+        &self,
+        block_number: i64,
+        tx_index: i64,
+    ) -> Result<Option<TransactionStateSet>, sqlx::Error>;
+}
+
+impl TransactionStateSetDB for DB {
+    /// Replaces every state set of the block with `state_sets`.
+    async fn replace_transaction_state_sets(
+        &self,
+        block_number: i64,
+        state_sets: &[TransactionStateSet],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM transaction_state_sets WHERE block_number = $1
+            "#,
+        )
+        .bind(block_number)
+        .execute(&mut *tx)
+        .await?;
+        for state_set in state_sets {
+            sqlx::query(
+                r#"
+                INSERT INTO transaction_state_sets (block_number, tx_index, read_set, write_set, authorities)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(state_set.block_number)
+            .bind(state_set.tx_index)
+            .bind(&state_set.read_set)
+            .bind(&state_set.write_set)
+            .bind(&state_set.authorities)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_transaction_state_sets_by_block_number(
+        &self,
+        block_number: i64,
+    ) -> Result<Vec<TransactionStateSet>, sqlx::Error> {
+        let state_sets = sqlx::query_as::<_, TransactionStateSet>(
+            r#"
+            SELECT * FROM transaction_state_sets WHERE block_number = $1 ORDER BY tx_index
+            "#,
+        )
+        .bind(block_number)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(state_sets)
+    }
+
+    async fn get_transaction_state_set(
+        &self,
+        block_number: i64,
+        tx_index: i64,
+    ) -> Result<Option<TransactionStateSet>, sqlx::Error> {
+        let state_set = sqlx::query_as::<_, TransactionStateSet>(
+            r#"
+            SELECT * FROM transaction_state_sets WHERE block_number = $1 AND tx_index = $2
+            "#,
+        )
+        .bind(block_number)
+        .bind(tx_index)
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(state_set)
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use eyre::eyre;
+
+use crate::{
+    config::BackfillDirection,
+    db::{
+        analysis_range::{AnalysisRangeDB, RANGE_DONE},
+        parallel_analyzer_state::ParallelAnalyzerStateDB,
+    },
+    models::{
+        analysis_ranges::{AnalysisRange, AnalysisRangesResponse},
+        common::AppError,
+    },
+    server::ServerState,
+};
+
+/// Backfill ranges with their progress, next to the head follower's.
+pub async fn handle_analysis_ranges(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<AnalysisRangesResponse>, AppError> {
+    let analyzer_state = state
+        .db
+        .get_parallel_analyzer_state_by_chainid(state.chain_id)
+        .await?
+        .ok_or(AppError(eyre!("parallel analyzer state not found")))?;
+    let ranges: Vec<AnalysisRange> = state
+        .db
+        .get_analysis_ranges_by_chainid(state.chain_id)
+        .await?
+        .into_iter()
+        .map(|r| {
+            let remaining_blocks = if r.status == RANGE_DONE {
+                0
+            } else if r.direction == BackfillDirection::Forward.as_str() {
+                r.end_block - r.next_block + 1
+            } else {
+                r.next_block - r.start_block + 1
+            };
+            AnalysisRange {
+                start_block: r.start_block,
+                end_block: r.end_block,
+                direction: r.direction,
+                next_block: r.next_block,
+                status: r.status,
+                worker: r.worker,
+                remaining_blocks,
+            }
+        })
+        .collect();
+    Ok(Json(AnalysisRangesResponse {
+        head_start_block: analyzer_state.start_block,
+        latest_analyzed_block: analyzer_state.latest_analyzed_block,
+        remaining_blocks: ranges.iter().map(|r| r.remaining_blocks).sum(),
+        ranges,
+    }))
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+use crate::{
+    db::block_parallelism_stats::BlockParallelismStatsDB,
+    handlers::common::resolve_block_range,
+    models::{
+        block_parallelism_stats::{
+            BlockParallelismStats, BlockParallelismStatsResponse, ParallelismSummary,
+        },
+        common::{AppError, BlockRangeQuery},
+    },
+    server::ServerState,
+};
+
+pub async fn handle_block_parallelism_stats(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<BlockRangeQuery>,
+) -> Result<Json<BlockParallelismStatsResponse>, AppError> {
+    let (start_block, end_block) = resolve_block_range(&state, &query).await?;
+    let stats = state
+        .db
+        .get_block_parallelism_stats_by_range(start_block, end_block)
+        .await?;
+    let blocks: Vec<BlockParallelismStats> = stats
+        .into_iter()
+        .map(|s| BlockParallelismStats {
+            block_number: s.block_number,
+            tx_count: s.tx_count,
+            pseudo_tx_count: s.pseudo_tx_count,
+            edge_count: s.edge_count,
+            balance_edge_count: s.balance_edge_count,
+            code_edge_count: s.code_edge_count,
+            storage_edge_count: s.storage_edge_count,
+            nonce_edge_count: s.nonce_edge_count,
+            critical_path_length: s.critical_path_length,
+            max_width: s.max_width,
+            components: s.components,
+            speedup: s.speedup,
+            total_gas: s.total_gas,
+            critical_path_gas: s.critical_path_gas,
+            gas_speedup: s.gas_speedup,
+            balance_exclusions: s.balance_exclusions.0,
+            naive_edge_count: s.naive_edge_count,
+            naive_critical_path_length: s.naive_critical_path_length,
+            naive_speedup: s.naive_speedup,
+            naive_critical_path_gas: s.naive_critical_path_gas,
+            naive_gas_speedup: s.naive_gas_speedup,
+        })
+        .collect();
+    let sum = |field: fn(&BlockParallelismStats) -> i64| blocks.iter().map(field).sum::<i64>();
+    let tx_count = sum(|b| b.tx_count);
+    let total_gas = sum(|b| b.total_gas);
+    let summary = ParallelismSummary {
+        block_count: blocks.len() as i64,
+        tx_count,
+        edge_count: sum(|b| b.edge_count),
+        speedup: ratio(tx_count, sum(|b| b.critical_path_length)),
+        total_gas,
+        gas_speedup: ratio(total_gas, sum(|b| b.critical_path_gas)),
+        naive_speedup: ratio(tx_count, sum(|b| b.naive_critical_path_length)),
+        naive_gas_speedup: ratio(total_gas, sum(|b| b.naive_critical_path_gas)),
+    };
+    Ok(Json(BlockParallelismStatsResponse {
+        start_block,
+        end_block,
+        summary,
+        blocks,
+    }))
+}
+
+/// Speedup over a range, 1 when there is nothing on the critical path.
+fn ratio(total: i64, critical_path: i64) -> f64 {
+    if critical_path == 0 {
+        1.0
+    } else {
+        total as f64 / critical_path as f64
+    }
+}
@@ -3,16 +3,22 @@ use std::{any::Any, sync::Arc};
 use axum::{
     Json,
     extract::State,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response as AxumResponse},
 };
+use eyre::eyre;
 use tracing::error;
 
 use crate::{
-    models::common::{AppError, HealthResp},
+    db::parallel_analyzer_state::ParallelAnalyzerStateDB,
+    metrics,
+    models::common::{AppError, BlockRangeQuery, HealthResp},
     server::ServerState,
 };
 
+/// Largest block range served by one request.
+pub const MAX_BLOCK_RANGE: i64 = 10_000;
+
 pub fn handle_panic(err: Box<dyn Any + Send + 'static>) -> AxumResponse {
     let detail = if let Some(s) = err.downcast_ref::<String>() {
         s.as_str()
@@ -42,3 +48,42 @@ pub async fn health_check(
 ) -> Result<Json<HealthResp>, AppError> {
     Ok(Json(HealthResp {}))
 }
+
+/// Analyzer error counters in the Prometheus text format. The counters live
+/// in the process that analyzes blocks, so they stay at zero under `serve`,
+/// where the analyzer runs in another process.
+pub async fn handle_metrics() -> AxumResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+        .into_response()
+}
+
+/// Resolves the inclusive block range of a query, defaulting to the latest
+/// analyzed block.
+pub async fn resolve_block_range(
+    state: &ServerState,
+    query: &BlockRangeQuery,
+) -> Result<(i64, i64), AppError> {
+    let start_block = if let Some(start_block) = query.start_block {
+        start_block
+    } else {
+        state
+            .db
+            .get_parallel_analyzer_state_by_chainid(state.chain_id)
+            .await?
+            .ok_or(AppError(eyre!("parallel analyzer state not found")))?
+            .latest_analyzed_block
+    };
+    let end_block = query.end_block.unwrap_or(start_block);
+    if end_block < start_block || end_block - start_block >= MAX_BLOCK_RANGE {
+        return Err(AppError(eyre!(
+            "invalid block range {}..={}, at most {} blocks",
+            start_block,
+            end_block,
+            MAX_BLOCK_RANGE
+        )));
+    }
+    Ok((start_block, end_block))
+}
@@ -0,0 +1,122 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response as AxumResponse},
+};
+use tracing::{error, info};
+
+use crate::{
+    db::failed_block::FailedBlockDB,
+    handlers::common::MAX_BLOCK_RANGE,
+    models::{
+        common::AppError,
+        failed_blocks::{
+            FailedBlock, FailedBlocksQuery, FailedBlocksResponse, RetryFailedBlocksResponse,
+        },
+    },
+    parallel_analyzer::ParallelAnalyzer,
+    server::ServerState,
+};
+
+const DEFAULT_FAILED_BLOCKS_LIMIT: i64 = 100;
+
+fn failed_blocks_limit(query: &FailedBlocksQuery) -> i64 {
+    query
+        .limit
+        .unwrap_or(DEFAULT_FAILED_BLOCKS_LIMIT)
+        .clamp(1, MAX_BLOCK_RANGE)
+}
+
+pub async fn handle_failed_blocks(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<FailedBlocksQuery>,
+) -> Result<Json<FailedBlocksResponse>, AppError> {
+    let failed_blocks = state
+        .db
+        .get_failed_blocks_by_chainid(state.chain_id, failed_blocks_limit(&query))
+        .await?
+        .into_iter()
+        .map(|b| FailedBlock {
+            block_number: b.block_number,
+            error_class: b.error_class,
+            error: b.error,
+            attempts: b.attempts,
+        })
+        .collect();
+    Ok(Json(FailedBlocksResponse { failed_blocks }))
+}
+
+/// Clears the in-flight flag when the retry finishes, panics included.
+struct RetryInFlight(Arc<AtomicBool>);
+
+impl Drop for RetryInFlight {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Whether the request carries `admin_token` as its bearer token.
+fn is_admin(state: &ServerState, headers: &HeaderMap) -> bool {
+    let Some(admin_token) = &state.config.admin_token else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token == admin_token)
+}
+
+/// Admin only: re-analyzes failed blocks in the background and returns which
+/// ones. Answers 409 while an earlier retry is still running.
+pub async fn handle_retry_failed_blocks(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<FailedBlocksQuery>,
+    headers: HeaderMap,
+) -> Result<AxumResponse, AppError> {
+    if state.config.admin_token.is_none() {
+        return Ok((StatusCode::NOT_FOUND, "admin_token is not configured.").into_response());
+    }
+    if !is_admin(&state, &headers) {
+        return Ok((StatusCode::UNAUTHORIZED, "invalid admin token.").into_response());
+    }
+    if state
+        .retry_in_flight
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        return Ok((
+            StatusCode::CONFLICT,
+            "failed blocks are already being retried.",
+        )
+            .into_response());
+    }
+    let in_flight = RetryInFlight(state.retry_in_flight.clone());
+    let queued: Vec<i64> = state
+        .db
+        .get_failed_blocks_by_chainid(state.chain_id, failed_blocks_limit(&query))
+        .await?
+        .into_iter()
+        .map(|b| b.block_number)
+        .collect();
+    let analyzer = ParallelAnalyzer::new(state.db.clone(), (*state.config).clone(), 0);
+    let block_numbers = queued.clone();
+    tokio::spawn(async move {
+        let _in_flight = in_flight;
+        match analyzer.retry_failed_blocks(&block_numbers).await {
+            Ok(recovered) => info!(
+                "Recovered {} of {} failed blocks",
+                recovered,
+                block_numbers.len()
+            ),
+            Err(e) => error!("Error retrying failed blocks: {:?}", e),
+        }
+    });
+    Ok(Json(RetryFailedBlocksResponse { queued }).into_response())
+}
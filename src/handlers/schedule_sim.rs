@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+
+use crate::{
+    db::block_schedule_sim::BlockScheduleSimDB,
+    handlers::common::resolve_block_range,
+    models::{
+        common::{AppError, BlockRangeQuery},
+        schedule_sim::{SpeedupCurvePoint, SpeedupCurveResponse},
+    },
+    server::ServerState,
+};
+
+pub async fn handle_speedup_curve(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<BlockRangeQuery>,
+) -> Result<Json<SpeedupCurveResponse>, AppError> {
+    let (start_block, end_block) = resolve_block_range(&state, &query).await?;
+    let totals = state
+        .db
+        .get_schedule_sim_totals_by_range(start_block, end_block)
+        .await?;
+    let points = totals
+        .into_iter()
+        .map(|t| {
+            let (utilization, speedup) = if t.makespan_gas == 0 {
+                (0.0, 1.0)
+            } else {
+                (
+                    t.total_gas as f64 / (t.workers * t.makespan_gas) as f64,
+                    t.total_gas as f64 / t.makespan_gas as f64,
+                )
+            };
+            let stm_speedup = if t.stm_makespan_gas == 0 {
+                1.0
+            } else {
+                t.total_gas as f64 / t.stm_makespan_gas as f64
+            };
+            SpeedupCurvePoint {
+                workers: t.workers,
+                block_count: t.block_count,
+                total_gas: t.total_gas,
+                makespan_gas: t.makespan_gas,
+                utilization,
+                speedup,
+                stm_makespan_gas: t.stm_makespan_gas,
+                stm_aborts: t.stm_aborts,
+                stm_wasted_gas: t.stm_wasted_gas,
+                stm_speedup,
+            }
+        })
+        .collect();
+    Ok(Json(SpeedupCurveResponse {
+        start_block,
+        end_block,
+        points,
+    }))
+}
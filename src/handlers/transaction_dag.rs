@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
 
 use alloy::providers::Provider;
 use axum::{
@@ -9,16 +9,23 @@ use eyre::eyre;
 
 use crate::{
     db::{
-        parallel_analyzer_state::ParallelAnalyzerStateDB, transaction::TransactionDB,
+        block::BlockDB,
+        parallel_analyzer_state::ParallelAnalyzerStateDB,
+        transaction::TransactionDB,
         transaction_dag::TransactionDagDB,
+        transaction_state_set::{StateSetRecord, TransactionStateSetDB},
     },
     models::{
         common::AppError,
         transaction_dag::{
-            ParallelAnalyzerStateResp, Transaction, TransactionDag, TransactionDagQuery,
-            TransactionDagResponse,
+            DagReason, ParallelAnalyzerStateResp, PseudoTransaction, StateSet, StorageSlot,
+            Transaction, TransactionDag, TransactionDagEdgeQuery, TransactionDagEdgeResponse,
+            TransactionDagQuery, TransactionDagResponse, TransactionStateSetQuery,
+            TransactionStateSetResponse,
         },
     },
+    parallel_analyzer::{dep_type_hazards, dep_type_resources},
+    pseudo_transactions::pseudo_transaction_name,
     server::ServerState,
 };
 
@@ -32,6 +39,12 @@ pub async fn handle_transaction_dag(
         let block_number = state.execution_api_client.get_block_number().await?;
         block_number as i64 - 10_i64
     };
+    let dag_version = state
+        .db
+        .get_block_by_number(block_number)
+        .await?
+        .map(|b| b.dag_version)
+        .unwrap_or_default();
     let transactions = state
         .db
         .get_transactions_by_block_number(block_number)
@@ -42,9 +55,12 @@ pub async fn handle_transaction_dag(
             index: t.tx_index,
             tx_hash: t.tx_hash,
             tx_type: t.tx_type,
-            gas_used: t.gas_price,
+            gas_used: t.gas_used.to_string(),
             from: t.tx_from,
             to: t.tx_to,
+            source_hash: t.source_hash,
+            mint: t.mint,
+            authorization_count: t.authorization_count,
         })
         .collect();
     let transaction_dags = state
@@ -57,15 +73,137 @@ pub async fn handle_transaction_dag(
             source: t.source_tx,
             target: t.target_tx,
             dep_type: t.dep_type,
+            resources: dep_type_resources(t.dep_type)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            hazards: dep_type_hazards(t.dep_type)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        })
+        .collect();
+    let pseudo_transactions = transaction_dags
+        .iter()
+        .flat_map(|dag| [dag.source, dag.target])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|index| {
+            pseudo_transaction_name(index, transactions.len()).map(|name| PseudoTransaction {
+                index,
+                name: name.to_string(),
+            })
         })
         .collect();
     Ok(Json(TransactionDagResponse {
         block_number,
+        dag_version,
         transactions,
+        pseudo_transactions,
         dags: transaction_dags,
     }))
 }
 
+pub async fn handle_transaction_dag_edge(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<TransactionDagEdgeQuery>,
+) -> Result<Json<TransactionDagEdgeResponse>, AppError> {
+    let edge = state
+        .db
+        .get_transaction_dag(query.block_number, query.source, query.target)
+        .await?
+        .ok_or(AppError(eyre!(
+            "transaction dag edge {} -> {} not found in block {}",
+            query.source,
+            query.target,
+            query.block_number
+        )))?;
+    let reasons = edge
+        .reasons
+        .map(|reasons| reasons.0)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| DagReason {
+            resource: r.resource,
+            hazard: r.hazard,
+            address: r.address,
+            slot: r.slot,
+            detail: r.detail,
+        })
+        .collect();
+    Ok(Json(TransactionDagEdgeResponse {
+        block_number: edge.block_number,
+        source: edge.source_tx,
+        target: edge.target_tx,
+        dep_type: edge.dep_type,
+        resources: dep_type_resources(edge.dep_type)
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        hazards: dep_type_hazards(edge.dep_type)
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        reasons,
+    }))
+}
+
+fn state_set_response(record: StateSetRecord) -> StateSet {
+    let strings = |addresses: Vec<_>| addresses.iter().map(ToString::to_string).collect();
+    StateSet {
+        balance: strings(record.balance),
+        code: strings(record.code),
+        nonce: strings(record.nonce),
+        storage: record
+            .storage
+            .into_iter()
+            .flat_map(|(address, slots)| {
+                slots.into_iter().map(move |slot| StorageSlot {
+                    address: address.to_string(),
+                    slot: slot.to_string(),
+                })
+            })
+            .collect(),
+    }
+}
+
+pub async fn handle_transaction_state_set(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<TransactionStateSetQuery>,
+) -> Result<Json<TransactionStateSetResponse>, AppError> {
+    let state_set = state
+        .db
+        .get_transaction_state_set(query.block_number, query.tx_index)
+        .await?
+        .ok_or(AppError(eyre!(
+            "read/write set of transaction {} not found in block {}",
+            query.tx_index,
+            query.block_number
+        )))?;
+    let transactions = state
+        .db
+        .get_transactions_by_block_number(query.block_number)
+        .await?;
+    let tx_hash = transactions
+        .iter()
+        .find(|t| t.tx_index == query.tx_index)
+        .map(|t| t.tx_hash.clone());
+    Ok(Json(TransactionStateSetResponse {
+        block_number: state_set.block_number,
+        tx_index: state_set.tx_index,
+        tx_hash,
+        pseudo_transaction: pseudo_transaction_name(state_set.tx_index, transactions.len())
+            .map(String::from),
+        read_set: state_set_response(state_set.read_set.0),
+        write_set: state_set_response(state_set.write_set.0),
+        authorities: state_set
+            .authorities
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    }))
+}
+
 pub async fn handle_parallel_analyzer_state(
     State(state): State<Arc<ServerState>>,
 ) -> Result<Json<ParallelAnalyzerStateResp>, AppError> {
@@ -81,5 +219,6 @@ pub async fn handle_parallel_analyzer_state(
         chain_id: analyzer_state.chain_id,
         start_block: analyzer_state.start_block,
         latest_analyzed_block: analyzer_state.latest_analyzed_block,
+        horizon: analyzer_state.horizon,
     }))
 }
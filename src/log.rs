@@ -7,6 +7,7 @@ pub fn init_log(level: &str) -> Result<()> {
     let env_filter = EnvFilter::try_new(level)?;
     tracing_subscriber::fmt::Subscriber::builder()
         .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
         // .with_thread_ids(true)
         // .with_thread_names(true)
         // .with_file(true)
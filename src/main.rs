@@ -1,25 +1,33 @@
 use std::sync::Arc;
 
 use clap::Parser;
-use db::{DB, parallel_analyzer_state::ParallelAnalyzerState};
+use db::DB;
 use eyre::{Context, Result};
-use server::ServerState;
 use shadow_rs::shadow;
 use sqlx::postgres::PgPoolOptions;
 use tracing::info;
 
 use crate::{
-    config::{Cli, Config},
-    db::parallel_analyzer_state::ParallelAnalyzerStateDB,
+    config::{AnalysisConfig, Cli, Command, Config, DatabaseCommand},
     log::init_log,
 };
 
+mod analyze_file;
+mod analyzer_error;
+mod block_analyzer;
+mod block_stm;
+mod chain_profile;
+mod commands;
 mod config;
+mod dag_metrics;
 mod db;
 mod handlers;
 mod log;
+mod metrics;
 mod models;
 mod parallel_analyzer;
+mod pseudo_transactions;
+mod rpc_cache;
 mod server;
 
 shadow!(build);
@@ -32,6 +40,13 @@ async fn main() -> Result<()> {
         return Ok(());
     }
     init_log("info")?;
+    let command = match cli.command {
+        Some(Command::AnalyzeFile { path, format }) => {
+            return analyze_file::analyze_file(AnalysisConfig::new(cli.config)?, &path, format);
+        }
+        Some(Command::Database(command)) => Some(command),
+        None => None,
+    };
     let config = Config::new(cli.config)?;
     info!("{:?}", config);
 
@@ -41,32 +56,29 @@ async fn main() -> Result<()> {
         .await
         .context("could not connect to database_url")?;
     let db = Arc::new(DB::new(db));
-    let server_state = ServerState::new(db.clone(), config.clone())?;
-    let parallel_analyzer_state = db
-        .get_parallel_analyzer_state_by_chainid(config.chain_id)
-        .await?;
-    let start_block = if let Some(state) = parallel_analyzer_state {
-        std::cmp::max(state.latest_analyzed_block + 1, config.start_block)
-    } else {
-        // init parallel_analyzer_state
-        db.insert_parallel_analyzer_state(&ParallelAnalyzerState {
-            latest_block: 0,
-            chain_id: config.chain_id,
-            start_block: config.start_block,
-            latest_analyzed_block: config.start_block - 1,
-            created_at: None,
-            updated_at: None,
-        })
-        .await?;
-        config.start_block
-    };
-    let parallel_analyzer = parallel_analyzer::ParallelAnalyzer::new(
-        db,
-        config.execution_api.clone(),
-        start_block,
-        config.chain_id,
-    );
-    let _ = tokio::join!(server_state.run(), parallel_analyzer.run());
-
-    Ok(())
+    match command {
+        None => commands::run(db, config).await,
+        Some(DatabaseCommand::Serve) => commands::serve(db, config).await,
+        Some(DatabaseCommand::Analyze {
+            from_block,
+            to_block,
+        }) => commands::analyze(db, config, from_block, to_block).await,
+        Some(DatabaseCommand::Backfill { workers }) => {
+            commands::backfill(db, config, workers).await
+        }
+        Some(DatabaseCommand::Reanalyze {
+            from_block,
+            to_block,
+            from_db,
+        }) => commands::reanalyze(db, config, from_block, to_block, from_db).await,
+        Some(DatabaseCommand::Export {
+            from_block,
+            to_block,
+            output,
+        }) => commands::export(db, from_block, to_block, output).await,
+        Some(DatabaseCommand::Migrate) => commands::migrate(db).await,
+        Some(DatabaseCommand::RetryFailed { limit }) => {
+            commands::retry_failed(db, config, limit).await
+        }
+    }
 }
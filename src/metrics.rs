@@ -0,0 +1,47 @@
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::analyzer_error::{AnalyzerError, ErrorClass, analyzer_error, classify_error};
+
+static ANALYZER_ERRORS: [AtomicU64; AnalyzerError::KINDS.len()] =
+    [const { AtomicU64::new(0) }; AnalyzerError::KINDS.len()];
+static ERROR_CLASSES: [AtomicU64; ErrorClass::ALL.len()] =
+    [const { AtomicU64::new(0) }; ErrorClass::ALL.len()];
+
+/// Counts a failed analyzer step by its class and, when it has one, its
+/// `AnalyzerError` variant.
+pub fn record_error(error: &eyre::Report) {
+    let class = classify_error(error);
+    ERROR_CLASSES[class as usize].fetch_add(1, Ordering::Relaxed);
+    if let Some(error) = analyzer_error(error) {
+        ANALYZER_ERRORS[error.kind_index()].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Renders the counters in the Prometheus text format.
+pub fn render() -> String {
+    let mut out = String::new();
+    out.push_str("# HELP analyzer_errors_total Analyzer failures by AnalyzerError variant.\n");
+    out.push_str("# TYPE analyzer_errors_total counter\n");
+    for (kind, count) in AnalyzerError::KINDS.iter().zip(&ANALYZER_ERRORS) {
+        let _ = writeln!(
+            out,
+            "analyzer_errors_total{{kind=\"{}\"}} {}",
+            kind,
+            count.load(Ordering::Relaxed)
+        );
+    }
+    out.push_str("# HELP analyzer_error_classes_total Analyzer failures by error class.\n");
+    out.push_str("# TYPE analyzer_error_classes_total counter\n");
+    for (class, count) in ErrorClass::ALL.iter().zip(&ERROR_CLASSES) {
+        let _ = writeln!(
+            out,
+            "analyzer_error_classes_total{{class=\"{}\"}} {}",
+            class,
+            count.load(Ordering::Relaxed)
+        );
+    }
+    out
+}
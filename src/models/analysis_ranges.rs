@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalysisRange {
+    pub start_block: i64,
+    pub end_block: i64,
+    /// "backward" or "forward"
+    pub direction: String,
+    pub next_block: i64,
+    /// "pending", "running" or "done"
+    pub status: String,
+    pub worker: Option<String>,
+    /// blocks of the range not analyzed yet
+    pub remaining_blocks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalysisRangesResponse {
+    /// first block of the head follower, the backfill stops below it
+    pub head_start_block: i64,
+    pub latest_analyzed_block: i64,
+    pub remaining_blocks: i64,
+    pub ranges: Vec<AnalysisRange>,
+}
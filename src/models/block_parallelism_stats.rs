@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockParallelismStats {
+    pub block_number: i64,
+    pub tx_count: i64,
+    /// pre-block system calls and withdrawals in the DAG, not in `tx_count`
+    pub pseudo_tx_count: i64,
+    pub edge_count: i64,
+    /// edges by the one resource that caused them, nonce before storage
+    /// before code before balance, summing to `edge_count`
+    pub balance_edge_count: i64,
+    pub code_edge_count: i64,
+    pub storage_edge_count: i64,
+    pub nonce_edge_count: i64,
+    /// longest dependency chain, which is also the number of topological levels
+    pub critical_path_length: i64,
+    pub max_width: i64,
+    pub components: i64,
+    pub speedup: f64,
+    pub total_gas: i64,
+    pub critical_path_gas: i64,
+    pub gas_speedup: f64,
+    /// addresses whose balance was left out of dependency detection
+    pub balance_exclusions: Vec<String>,
+    /// metrics of the DAG built without exclusions
+    pub naive_edge_count: i64,
+    pub naive_critical_path_length: i64,
+    pub naive_speedup: f64,
+    pub naive_critical_path_gas: i64,
+    pub naive_gas_speedup: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParallelismSummary {
+    pub block_count: i64,
+    pub tx_count: i64,
+    pub edge_count: i64,
+    /// total tx_count / total critical_path_length over the range
+    pub speedup: f64,
+    pub total_gas: i64,
+    /// total_gas / total critical_path_gas over the range
+    pub gas_speedup: f64,
+    /// the same ratios for the DAGs built without exclusions
+    pub naive_speedup: f64,
+    pub naive_gas_speedup: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockParallelismStatsResponse {
+    pub start_block: i64,
+    pub end_block: i64,
+    pub summary: ParallelismSummary,
+    pub blocks: Vec<BlockParallelismStats>,
+}
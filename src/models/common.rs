@@ -32,3 +32,11 @@ pub struct FailResponse {
     pub code: u16,
     pub message: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockRangeQuery {
+    /// defaults to the latest analyzed block
+    pub start_block: Option<i64>,
+    /// defaults to `start_block`
+    pub end_block: Option<i64>,
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedBlocksQuery {
+    /// defaults to 100
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedBlock {
+    pub block_number: i64,
+    /// "transient_rpc", "missing_block", "tracer_unsupported", "database" or
+    /// "other"
+    pub error_class: String,
+    pub error: String,
+    pub attempts: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedBlocksResponse {
+    pub failed_blocks: Vec<FailedBlock>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryFailedBlocksResponse {
+    /// blocks re-analyzed in the background
+    pub queued: Vec<i64>,
+}
@@ -1,2 +1,6 @@
+pub mod analysis_ranges;
+pub mod block_parallelism_stats;
 pub mod common;
+pub mod failed_blocks;
+pub mod schedule_sim;
 pub mod transaction_dag;
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeedupCurvePoint {
+    pub workers: i64,
+    pub block_count: i64,
+    pub total_gas: i64,
+    pub makespan_gas: i64,
+    /// total_gas / (workers * makespan_gas)
+    pub utilization: f64,
+    /// total_gas / makespan_gas
+    pub speedup: f64,
+    /// Block-STM replay with `workers` threads
+    pub stm_makespan_gas: i64,
+    pub stm_aborts: i64,
+    pub stm_wasted_gas: i64,
+    /// total_gas / stm_makespan_gas
+    pub stm_speedup: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpeedupCurveResponse {
+    pub start_block: i64,
+    pub end_block: i64,
+    pub points: Vec<SpeedupCurvePoint>,
+}
@@ -13,6 +13,20 @@ pub struct Transaction {
     pub gas_used: String,
     pub from: String,
     pub to: String,
+    /// deposit transactions only, none when the node left the field out
+    pub source_hash: Option<String>,
+    pub mint: Option<String>,
+    /// EIP-7702 authorizations of a set-code transaction
+    pub authorization_count: i64,
+}
+
+/// Pre-block system calls or post-block withdrawals, in the DAG when the
+/// analyzer runs with `pseudo_transactions`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PseudoTransaction {
+    pub index: i64,
+    /// "system_calls" or "withdrawals"
+    pub name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,19 +34,97 @@ pub struct TransactionDag {
     pub source: i64,
     pub target: i64,
     pub dep_type: i16,
+    /// resources behind the edge: "balance", "code", "storage" and/or "nonce"
+    pub resources: Vec<String>,
+    /// hazard classes behind the edge: "raw", "waw" and/or "war"
+    pub hazards: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionDagEdgeQuery {
+    pub block_number: i64,
+    pub source: i64,
+    pub target: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DagReason {
+    /// "balance", "code", "storage" or "nonce"
+    pub resource: String,
+    /// "raw", "waw" or "war"
+    pub hazard: String,
+    pub address: String,
+    pub slot: Option<String>,
+    /// e.g. "eip-7702 delegation of authority 0x..."
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionDagEdgeResponse {
+    pub block_number: i64,
+    pub source: i64,
+    pub target: i64,
+    pub dep_type: i16,
+    pub resources: Vec<String>,
+    pub hazards: Vec<String>,
+    /// empty for edges stored before reasons were recorded
+    pub reasons: Vec<DagReason>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TransactionDagResponse {
     pub block_number: i64,
+    /// version of the dependency algorithm that produced `dags`, 0 if the
+    /// block has not been analyzed
+    pub dag_version: i16,
     pub transactions: Vec<Transaction>,
+    /// pseudo-transactions with edges in `dags`
+    pub pseudo_transactions: Vec<PseudoTransaction>,
     pub dags: Vec<TransactionDag>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionStateSetQuery {
+    pub block_number: i64,
+    /// index of a transaction or a pseudo-transaction
+    pub tx_index: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageSlot {
+    pub address: String,
+    pub slot: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StateSet {
+    pub balance: Vec<String>,
+    pub code: Vec<String>,
+    pub nonce: Vec<String>,
+    pub storage: Vec<StorageSlot>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionStateSetResponse {
+    pub block_number: i64,
+    pub tx_index: i64,
+    /// none for pseudo-transactions
+    pub tx_hash: Option<String>,
+    /// "system_calls" or "withdrawals" for pseudo-transactions
+    pub pseudo_transaction: Option<String>,
+    pub read_set: StateSet,
+    pub write_set: StateSet,
+    /// EIP-7702 authorities whose delegation the transaction applied
+    pub authorities: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ParallelAnalyzerStateResp {
     pub latest_block: i64,
     pub chain_id: i64,
     pub start_block: i64,
     pub latest_analyzed_block: i64,
+    /// how final `latest_block` is: "latest", "safe", "finalized" or
+    /// "latest-N"
+    pub horizon: String,
 }
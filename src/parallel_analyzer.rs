@@ -1,69 +1,369 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    hash::Hash,
     sync::Arc,
+    time::Duration,
 };
 
 use alloy::{
-    consensus::Transaction,
-    eips::BlockNumberOrTag,
-    network::Ethereum,
+    consensus::{Transaction, Typed2718},
+    eips::{BlockId, BlockNumberOrTag},
+    network::{
+        AnyNetwork, AnyRpcBlock as AlloyBlock, AnyRpcTransaction as AlloyTransaction,
+        AnyTransactionReceipt as TransactionReceipt, TransactionResponse,
+    },
     primitives::{Address, B256, TxHash},
     providers::{Provider, RootProvider, ext::DebugApi},
-    rpc::types::{
-        Transaction as AlloyTransaction,
-        trace::geth::{AccountState, GethDebugTracingOptions, PreStateConfig},
+    rpc::{
+        client::ClientBuilder,
+        types::trace::geth::{
+            AccountState, GethDebugTracingOptions, GethTrace, PreStateConfig, TraceResult,
+        },
     },
 };
-use eyre::Result;
-use reqwest::Url;
-use tracing::{debug, error, info};
-
-use crate::db::{
-    DB,
-    block::{Block, BlockDB},
-    parallel_analyzer_state::ParallelAnalyzerStateDB,
-    transaction::{Transaction as DbTransaction, TransactionDB},
-    transaction_dag::{TransactionDag, TransactionDagDB},
+use eyre::{Result, eyre};
+use futures::{Stream, StreamExt, TryStreamExt, future, stream};
+use sqlx::types::Json;
+use tokio::{sync::Semaphore, task::JoinHandle};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    analyzer_error::{AnalyzerError, ErrorClass, analyzer_error, classify_error},
+    block_analyzer::BlockAnalyzer,
+    block_stm::{BlockStmSim, simulate_block_stm},
+    config::{AnalysisConfig, AnalysisHorizon, BackfillConfig, BackfillDirection, Config},
+    dag_metrics::{DagMetrics, ScheduleSim, dag_metrics, simulate_schedules},
+    db::{
+        DB,
+        analysis_range::{
+            AnalysisRange, AnalysisRangeDB, RANGE_DONE, RANGE_PENDING, RANGE_RUNNING,
+        },
+        block::{Block, BlockDB},
+        block_parallelism_stats::BlockParallelismStats,
+        block_schedule_sim::BlockScheduleSim,
+        failed_block::{FailedBlock, FailedBlockDB},
+        parallel_analyzer_state::ParallelAnalyzerStateDB,
+        reorg::{Reorg, ReorgDB},
+        transaction::{Transaction as DbTransaction, TransactionDB},
+        transaction_dag::{DagReason, TransactionDag},
+        transaction_state_set::{
+            StateSetRecord, TransactionStateSet as DbTransactionStateSet, TransactionStateSetDB,
+        },
+    },
+    metrics,
+    pseudo_transactions::{pseudo_transaction_name, pseudo_transaction_states},
+    rpc_cache::RpcCacheLayer,
 };
 
+/// Version of the dependency algorithm, recorded in `blocks.dag_version`.
+///
+/// 1: storage keyed by bare slot, 2: storage keyed by (address, slot), 3:
+/// write-after-write and write-after-read hazards, 4: dependencies on
+/// transaction 0, 5: nonce dependencies, 6: chain profile system addresses
+/// and transactions left out, OP-stack deposits, 7: EIP-7702 authorities
+pub const DAG_VERSION: i16 = 7;
+
+/// Resource nibbles of `dep_type`. Each nibble holds the `HAZARD_*` bits for
+/// that resource, so the RAW bits keep their original values.
+pub const DEP_BALANCE: i16 = 0x1;
+pub const DEP_CODE: i16 = 0x10;
+pub const DEP_STORAGE: i16 = 0x100;
+pub const DEP_NONCE: i16 = 0x1000;
+
+const DEP_RESOURCES: [(i16, &str); 4] = [
+    (DEP_BALANCE, "balance"),
+    (DEP_CODE, "code"),
+    (DEP_STORAGE, "storage"),
+    (DEP_NONCE, "nonce"),
+];
+
+/// Hazard bits within a resource nibble.
+pub const HAZARD_RAW: i16 = 0x1;
+pub const HAZARD_WAW: i16 = 0x2;
+pub const HAZARD_WAR: i16 = 0x4;
+
 #[derive(Clone)]
 pub struct ParallelAnalyzer {
     pub db: Arc<DB>,
-    pub execution_api_client: Arc<RootProvider<Ethereum>>,
+    pub config: Arc<Config>,
+    pub execution_api_client: Arc<RootProvider<AnyNetwork>>,
+    /// bounds the trace requests in flight to `trace_concurrency`
+    pub trace_permits: Arc<Semaphore>,
     pub start_block: i64,
     pub chain_id: i64,
+    pub block_analyzer: BlockAnalyzer,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct StateSet {
     pub balance_set: HashSet<Address>,
     pub code_set: HashSet<Address>,
-    pub storage_set: HashSet<B256>,
+    /// (contract address, storage slot)
+    pub storage_set: HashSet<(Address, B256)>,
+    pub nonce_set: HashSet<Address>,
 }
 
-#[derive(Debug, Clone)]
+/// A single piece of state a transaction can read or write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StateKey {
+    Balance(Address),
+    Code(Address),
+    Storage(Address, B256),
+    Nonce(Address),
+}
+
+impl StateKey {
+    pub fn resource_name(&self) -> &'static str {
+        match self {
+            StateKey::Balance(_) => "balance",
+            StateKey::Code(_) => "code",
+            StateKey::Storage(..) => "storage",
+            StateKey::Nonce(_) => "nonce",
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            StateKey::Balance(address)
+            | StateKey::Code(address)
+            | StateKey::Storage(address, _)
+            | StateKey::Nonce(address) => *address,
+        }
+    }
+
+    pub fn slot(&self) -> Option<B256> {
+        match self {
+            StateKey::Storage(_, slot) => Some(*slot),
+            _ => None,
+        }
+    }
+}
+
+/// A piece of state behind a dependency and the hazard it causes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Conflict {
+    pub key: StateKey,
+    /// one of `HAZARD_*`
+    pub hazard: i16,
+    /// the key is the code of an EIP-7702 authority delegated by one of the
+    /// two transactions
+    pub delegation: bool,
+}
+
+impl From<&Conflict> for DagReason {
+    fn from(conflict: &Conflict) -> Self {
+        DagReason {
+            resource: conflict.key.resource_name().to_string(),
+            hazard: hazard_name(conflict.hazard).to_string(),
+            address: conflict.key.address().to_string(),
+            slot: conflict.key.slot().map(|slot| slot.to_string()),
+            detail: conflict.delegation.then(|| {
+                format!(
+                    "eip-7702 delegation of authority {}",
+                    conflict.key.address()
+                )
+            }),
+        }
+    }
+}
+
+impl StateSet {
+    pub fn keys(&self) -> impl Iterator<Item = StateKey> + '_ {
+        self.balance_set
+            .iter()
+            .map(|address| StateKey::Balance(*address))
+            .chain(self.code_set.iter().map(|address| StateKey::Code(*address)))
+            .chain(
+                self.storage_set
+                    .iter()
+                    .map(|(address, slot)| StateKey::Storage(*address, *slot)),
+            )
+            .chain(
+                self.nonce_set
+                    .iter()
+                    .map(|address| StateKey::Nonce(*address)),
+            )
+    }
+}
+
+impl From<&StateSet> for StateSetRecord {
+    fn from(set: &StateSet) -> Self {
+        let sorted = |addresses: &HashSet<Address>| {
+            let mut addresses: Vec<Address> = addresses.iter().copied().collect();
+            addresses.sort();
+            addresses
+        };
+        let mut storage: BTreeMap<Address, Vec<B256>> = BTreeMap::new();
+        for (address, slot) in &set.storage_set {
+            storage.entry(*address).or_default().push(*slot);
+        }
+        storage.values_mut().for_each(|slots| slots.sort());
+        StateSetRecord {
+            balance: sorted(&set.balance_set),
+            code: sorted(&set.code_set),
+            nonce: sorted(&set.nonce_set),
+            storage,
+        }
+    }
+}
+
+impl From<&StateSetRecord> for StateSet {
+    fn from(record: &StateSetRecord) -> Self {
+        StateSet {
+            balance_set: record.balance.iter().copied().collect(),
+            code_set: record.code.iter().copied().collect(),
+            storage_set: record
+                .storage
+                .iter()
+                .flat_map(|(address, slots)| slots.iter().map(|slot| (*address, *slot)))
+                .collect(),
+            nonce_set: record.nonce.iter().copied().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct TransactionStateSet {
     pub read_set: StateSet,
     pub write_set: StateSet,
+    /// EIP-7702 authorities whose delegation the transaction applied
+    pub authorities: HashSet<Address>,
+}
+
+impl From<&DbTransactionStateSet> for TransactionStateSet {
+    fn from(stored: &DbTransactionStateSet) -> Self {
+        TransactionStateSet {
+            read_set: StateSet::from(&stored.read_set.0),
+            write_set: StateSet::from(&stored.write_set.0),
+            authorities: stored.authorities.iter().copied().collect(),
+        }
+    }
+}
+
+impl TransactionStateSet {
+    /// Row of `transaction_state_sets` for the transaction at `tx_index`.
+    pub fn to_stored(&self, block_number: i64, tx_index: i64) -> DbTransactionStateSet {
+        let mut authorities: Vec<Address> = self.authorities.iter().copied().collect();
+        authorities.sort();
+        DbTransactionStateSet {
+            block_number,
+            tx_index,
+            read_set: Json(StateSetRecord::from(&self.read_set)),
+            write_set: Json(StateSetRecord::from(&self.write_set)),
+            authorities: Json(authorities),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// `source` depends on the earlier transaction `target`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DagEdge {
+    pub source: i64,
+    pub target: i64,
+    pub dep_type: i16,
+    /// conflicting keys, sorted
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Dependency DAG, metrics and simulations derived from one block's traces.
+#[derive(Debug, Clone)]
+pub struct BlockAnalysis {
+    pub edges: Vec<DagEdge>,
+    pub metrics: DagMetrics,
+    /// metrics without `DependencyRules::balance_exclusions`
+    pub naive_metrics: DagMetrics,
+    pub schedules: Vec<ScheduleSim>,
+    pub stm_sims: Vec<BlockStmSim>,
+}
+
+/// The cursor a block is processed for.
+#[derive(Debug, Clone, Copy)]
+enum Cursor {
+    /// the head follower, reading up to `latest_block_number`
+    Head { latest_block_number: i64 },
+    /// a backfill worker or a one-off block range, left out of the analyzer
+    /// state
+    Backfill,
+}
+
+/// Aborts a spawned task when dropped.
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Everything fetched from the execution api to analyse one block.
+#[derive(Debug, Clone)]
+pub struct BlockData {
+    pub block: AlloyBlock,
+    pub receipts: Vec<TransactionReceipt>,
+    pub tx_states: BTreeMap<i64, TransactionStateSet>,
+}
+
+impl BlockData {
+    /// Gas used by every transaction, keyed by transaction index.
+    pub fn gas_used(&self) -> Result<BTreeMap<i64, u64>, AnalyzerError> {
+        self.receipts
+            .iter()
+            .map(|receipt| {
+                let tx_index =
+                    receipt
+                        .transaction_index
+                        .ok_or(AnalyzerError::MissingTransactionIndex(
+                            receipt.transaction_hash,
+                        ))? as i64;
+                Ok((tx_index, receipt.gas_used))
+            })
+            .collect()
+    }
 }
 
 impl ParallelAnalyzer {
-    pub fn new(db: Arc<DB>, execution_api: Url, start_block: i64, chain_id: i64) -> Self {
-        let provider = RootProvider::builder().on_http(execution_api);
+    pub fn new(db: Arc<DB>, config: Config, start_block: i64) -> Self {
+        let client = ClientBuilder::default()
+            .layer(RpcCacheLayer::new(&config.rpc_cache))
+            .http(config.execution_api.clone());
+        let provider = RootProvider::builder().on_client(client);
         Self {
             db,
             execution_api_client: Arc::new(provider),
+            trace_permits: Arc::new(Semaphore::new(config.trace_concurrency.max(1))),
             start_block,
-            chain_id,
+            chain_id: config.chain_id,
+            block_analyzer: BlockAnalyzer::new(AnalysisConfig::from(&config)),
+            config: Arc::new(config),
         }
     }
 
-    pub async fn get_block_transactions(&self, block_number: u64) -> Result<Vec<AlloyTransaction>> {
+    pub async fn get_block(&self, block_number: u64) -> Result<AlloyBlock> {
         let full_block = self
             .execution_api_client
             .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .full()
             .await?
-            .expect("Block not found");
+            .ok_or(AnalyzerError::BlockNotFound(block_number as i64))?;
+        Ok(full_block)
+    }
+
+    pub async fn get_block_receipts(&self, block_number: u64) -> Result<Vec<TransactionReceipt>> {
+        let receipts = self
+            .execution_api_client
+            .get_block_receipts(BlockId::number(block_number))
+            .await?
+            .ok_or(AnalyzerError::ReceiptsNotFound(block_number as i64))?;
+        Ok(receipts)
+    }
+
+    pub async fn store_block(
+        &self,
+        full_block: &AlloyBlock,
+        gas_used: &BTreeMap<i64, u64>,
+    ) -> Result<()> {
         let data = Block {
             parent_hash: full_block.header.parent_hash.to_string(),
             block_hash: full_block.header.hash.to_string(),
@@ -74,25 +374,27 @@ impl ParallelAnalyzer {
             base_fee_per_gas: full_block.header.base_fee_per_gas.unwrap_or_default() as i64,
             blob_gas_used: full_block.header.blob_gas_used.unwrap_or_default() as i64,
             excess_blob_gas: full_block.header.excess_blob_gas.unwrap_or_default() as i64,
+            dag_version: 0,
+            fee_recipient: Some(full_block.header.beneficiary.to_string()),
             created_at: None,
             updated_at: None,
         };
         self.db.insert_block(&data).await?;
-        let transactions = full_block
-            .transactions
-            .as_transactions()
-            .expect("convert to inner transaction failed")
-            .to_vec();
-        for tx in transactions.clone() {
-            let tx_req = tx.clone().into_request();
+        for tx in block_transactions(full_block)? {
+            let tx_index = tx_index(&tx)?;
+            let (source_hash, mint) = self.block_analyzer.deposit_fields(&tx);
             let data = DbTransaction {
-                block_number: tx.block_number.expect("block number not found") as i64,
-                tx_index: tx.transaction_index.expect("transaction index not found") as i64,
-                tx_hash: tx.inner.tx_hash().to_string(),
-                tx_from: tx_req.from.unwrap_or_default().to_string(),
+                block_number: tx
+                    .block_number
+                    .ok_or(AnalyzerError::MissingBlockNumber(tx.tx_hash()))?
+                    as i64,
+                tx_index,
+                tx_hash: tx.tx_hash().to_string(),
+                tx_from: tx.from().to_string(),
+                authorization_count: tx.authorization_list().map_or(0, <[_]>::len) as i64,
                 tx_to: tx.to().unwrap_or_default().to_string(),
-                gas_price: tx.gas_price().unwrap_or_default().to_string(),
-                max_fee_per_gas: tx.max_fee_per_gas().to_string(),
+                gas_price: Transaction::gas_price(&tx).unwrap_or_default().to_string(),
+                max_fee_per_gas: Transaction::max_fee_per_gas(&tx).to_string(),
                 max_priority_fee_per_gas: tx
                     .max_priority_fee_per_gas()
                     .unwrap_or_default()
@@ -102,143 +404,1028 @@ impl ParallelAnalyzer {
                 tx_value: tx.value().to_string(),
                 input: tx.input().to_string(),
                 nonce: tx.nonce() as i64,
-                tx_type: tx.inner.tx_type() as i16,
+                tx_type: tx.ty() as i16,
+                gas_used: gas_used.get(&tx_index).copied().unwrap_or_default() as i64,
+                source_hash: source_hash.map(|source_hash| source_hash.to_string()),
+                mint: mint.map(|mint| mint.to_string()),
                 created_at: None,
                 updated_at: None,
             };
             self.db.insert_transaction(&data).await?;
         }
-        Ok(transactions)
+        Ok(())
     }
 
-    pub async fn trace_transaction_state(&self, tx_hash: &TxHash) -> Result<TransactionStateSet> {
+    pub async fn trace_transaction_state(
+        &self,
+        tx: &AlloyTransaction,
+    ) -> Result<TransactionStateSet> {
+        let tx_hash = tx.tx_hash();
+        let _permit = self.trace_permits.acquire().await?;
         // fetch transaction read states
         let read_trace = self
             .execution_api_client
-            .debug_trace_transaction(
-                *tx_hash,
-                GethDebugTracingOptions::prestate_tracer(PreStateConfig::default()),
-            )
+            .debug_trace_transaction(tx_hash, prestate_tracing_options(false))
             .await?;
-        // balance read
-        let frame = read_trace
-            .try_into_pre_state_frame()
-            .expect("pre state frame not found");
-        let read_state = frame.as_default().expect("frame parse failed").clone().0;
         // fetch transaction write states
         let write_trace = self
             .execution_api_client
-            .debug_trace_transaction(
-                *tx_hash,
-                GethDebugTracingOptions::prestate_tracer(PreStateConfig {
-                    diff_mode: Some(true),
-                    ..Default::default()
-                }),
-            )
+            .debug_trace_transaction(tx_hash, prestate_tracing_options(true))
             .await?;
-        let frame = write_trace
-            .try_into_pre_state_frame()
-            .expect("frame not found");
-        let write_state = frame
-            .as_diff()
-            .expect("convert to diff mode failed")
-            .post
-            .clone();
-        let read_set = account_state_to_set(read_state);
+        let state = traces_to_state_set(
+            tx_hash,
+            read_trace,
+            write_trace,
+            tx.from(),
+            &tx_authorities(tx),
+        )?;
         debug!(
             "tx_hash: {:?}, Read set: {:?}",
-            tx_hash, read_set.storage_set
+            tx_hash, state.read_set.storage_set
         );
-        let write_set = account_state_to_set(write_state);
         debug!(
             "tx_hash: {:?}, Write set: {:?}",
-            tx_hash, write_set.storage_set
+            tx_hash, state.write_set.storage_set
         );
+        Ok(state)
+    }
+
+    /// Traces every transaction of the block with two
+    /// `debug_traceBlockByNumber` calls, one in prestate mode and one in diff
+    /// mode, keyed by transaction index.
+    pub async fn trace_block_state(
+        &self,
+        block_number: u64,
+        transactions: &[AlloyTransaction],
+    ) -> Result<BTreeMap<i64, TransactionStateSet>> {
+        let trace = |diff_mode| async move {
+            let _permit = self.trace_permits.acquire().await?;
+            let traces = self
+                .execution_api_client
+                .debug_trace_block_by_number(
+                    BlockNumberOrTag::Number(block_number),
+                    prestate_tracing_options(diff_mode),
+                )
+                .await?;
+            Ok::<_, eyre::Report>(traces)
+        };
+        let (read_traces, write_traces) = tokio::try_join!(trace(false), trace(true))?;
+        Ok(block_traces_to_states(
+            block_number as i64,
+            transactions,
+            read_traces,
+            write_traces,
+        )?)
+    }
+
+    /// Traces the transactions one by one, for nodes that reject block
+    /// tracing. Up to `trace_concurrency` transactions are traced at once.
+    pub async fn trace_transactions_state(
+        &self,
+        transactions: &[AlloyTransaction],
+    ) -> Result<BTreeMap<i64, TransactionStateSet>> {
+        // owned transactions keep the stream `Send` for spawned retries
+        stream::iter(transactions.iter().cloned())
+            .map(|tx| async move {
+                let tx_index = tx_index(&tx)?;
+                let state = self.trace_transaction_state(&tx).await?;
+                Ok::<_, eyre::Report>((tx_index, state))
+            })
+            .buffered(self.config.trace_concurrency.max(1))
+            .try_collect()
+            .await
+    }
 
-        Ok(TransactionStateSet {
-            read_set,
-            write_set,
+    /// Fetches a block and traces all of its transactions, preferring block
+    /// tracing and falling back to per-transaction tracing.
+    pub async fn fetch_block_data(&self, block_number: i64) -> Result<BlockData> {
+        let (block, receipts) = tokio::try_join!(
+            self.get_block(block_number as u64),
+            self.get_block_receipts(block_number as u64)
+        )?;
+        let transactions = block_transactions(&block)?;
+        let tx_states = match self
+            .trace_block_state(block_number as u64, &transactions)
+            .await
+        {
+            Ok(tx_states) => tx_states,
+            Err(e) => {
+                warn!(
+                    "Block tracing failed for block {}, falling back to per-transaction tracing: {:?}",
+                    block_number, e
+                );
+                self.trace_transactions_state(&transactions).await?
+            }
+        };
+        Ok(BlockData {
+            block,
+            receipts,
+            tx_states,
         })
     }
 
-    pub async fn analyse_block(&self, block_number: i64, latest_block_number: i64) -> Result<()> {
-        let transactions = self.get_block_transactions(block_number as u64).await?;
-        let mut tx_states = BTreeMap::new();
-        for tx in transactions {
-            let tx_hash = tx.inner.tx_hash();
-            let tx_index = tx.transaction_index.expect("transaction index not found") as i64;
-            let state = self.trace_transaction_state(tx_hash).await?;
-            tx_states.insert(tx_index, state);
-        }
+    /// `block_numbers` with their data in order, each fetched and traced in a
+    /// task of its own up to `block_prefetch` blocks ahead of the one taken.
+    /// Dropping the stream aborts the blocks fetched ahead.
+    fn prefetch_blocks(
+        &self,
+        block_numbers: impl IntoIterator<Item = i64>,
+    ) -> impl Stream<Item = (i64, Result<BlockData>)> + Unpin {
+        let analyzer = self.clone();
+        stream::iter(block_numbers)
+            .map(move |number| {
+                let analyzer = analyzer.clone();
+                let mut fetch = AbortOnDrop(tokio::spawn(async move {
+                    analyzer.fetch_block_data(number).await
+                }));
+                async move {
+                    let data = (&mut fetch.0).await.unwrap_or_else(|e| Err(e.into()));
+                    (number, data)
+                }
+            })
+            .buffered(self.config.block_prefetch + 1)
+    }
+
+    /// Stores the block, its transactions, their read/write sets and its
+    /// dependency DAG.
+    pub async fn store_block_data(&self, data: &BlockData) -> Result<()> {
+        let block_number = data.block.header.number as i64;
+        let gas_used = data.gas_used()?;
+        let (rules, analysis) = self.block_analyzer.block_analysis(data, &gas_used)?;
+        self.store_block(&data.block, &gas_used).await?;
+        self.store_tx_states(data).await?;
+        self.store_analysis(block_number, &rules, &analysis).await
+    }
+
+    /// Stores the read/write sets of every transaction of the block, system
+    /// transactions and pseudo-transactions included, so that the block can
+    /// be re-analysed under other settings without the execution api.
+    pub async fn store_tx_states(&self, data: &BlockData) -> Result<()> {
+        let block_number = data.block.header.number as i64;
+        let tx_count = block_transactions(&data.block)?.len();
+        let state_sets: Vec<DbTransactionStateSet> = data
+            .tx_states
+            .iter()
+            .map(|(tx_index, state)| (*tx_index, state.clone()))
+            .chain(pseudo_transaction_states(&data.block, tx_count))
+            .map(|(tx_index, state)| state.to_stored(block_number, tx_index))
+            .collect();
         self.db
-            .delete_transaction_dags_by_block_number(block_number)
+            .replace_transaction_state_sets(block_number, &state_sets)
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces the DAG, stats and simulations of a stored block and marks it
+    /// analysed with the current `DAG_VERSION`, in one transaction.
+    pub async fn store_analysis(
+        &self,
+        block_number: i64,
+        rules: &DependencyRules,
+        analysis: &BlockAnalysis,
+    ) -> Result<()> {
+        let dags: Vec<TransactionDag> = analysis
+            .edges
+            .iter()
+            .map(|edge| {
+                debug!(
+                    "Transaction {} depends on transaction {} with mask {:x}",
+                    edge.source, edge.target, edge.dep_type
+                );
+                TransactionDag {
+                    block_number,
+                    source_tx: edge.source,
+                    target_tx: edge.target,
+                    dep_type: edge.dep_type,
+                    reasons: Some(Json(edge.conflicts.iter().map(DagReason::from).collect())),
+                    created_at: None,
+                    updated_at: None,
+                }
+            })
+            .collect();
+        let (metrics, naive) = (&analysis.metrics, &analysis.naive_metrics);
+        let mut balance_exclusions: Vec<String> = rules
+            .balance_exclusions
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+        balance_exclusions.sort();
+        let stats = BlockParallelismStats {
+            block_number,
+            tx_count: metrics.tx_count,
+            pseudo_tx_count: metrics.pseudo_tx_count,
+            edge_count: metrics.edge_count,
+            balance_edge_count: metrics.balance_edge_count,
+            code_edge_count: metrics.code_edge_count,
+            storage_edge_count: metrics.storage_edge_count,
+            nonce_edge_count: metrics.nonce_edge_count,
+            critical_path_length: metrics.critical_path_length,
+            max_width: metrics.max_width,
+            components: metrics.components,
+            speedup: metrics.speedup,
+            total_gas: metrics.total_gas,
+            critical_path_gas: metrics.critical_path_gas,
+            gas_speedup: metrics.gas_speedup,
+            balance_exclusions: Json(balance_exclusions),
+            naive_edge_count: naive.edge_count,
+            naive_critical_path_length: naive.critical_path_length,
+            naive_speedup: naive.speedup,
+            naive_critical_path_gas: naive.critical_path_gas,
+            naive_gas_speedup: naive.gas_speedup,
+            created_at: None,
+            updated_at: None,
+        };
+        let sims: Vec<BlockScheduleSim> = analysis
+            .schedules
+            .iter()
+            .zip(&analysis.stm_sims)
+            .map(|(sim, stm)| BlockScheduleSim {
+                block_number,
+                workers: sim.workers as i64,
+                makespan_gas: sim.makespan_gas as i64,
+                utilization: sim.utilization,
+                speedup: sim.speedup,
+                stm_makespan_gas: stm.makespan_gas as i64,
+                stm_aborts: stm.aborts as i64,
+                stm_wasted_gas: stm.wasted_gas as i64,
+                stm_speedup: stm.speedup,
+                created_at: None,
+                updated_at: None,
+            })
+            .collect();
+        self.db
+            .replace_block_analysis(block_number, &dags, &stats, &sims, DAG_VERSION)
+            .await?;
+        Ok(())
+    }
+
+    /// Re-analyses a stored block from its `transaction_state_sets` under the
+    /// current settings, without the execution api.
+    pub async fn reanalyze_stored_block(&self, block_number: i64) -> Result<()> {
+        let block = self
+            .db
+            .get_block_by_number(block_number)
+            .await?
+            .ok_or(AnalyzerError::BlockNotFound(block_number))?;
+        let state_sets = self
+            .db
+            .get_transaction_state_sets_by_block_number(block_number)
             .await?;
-        for (tx_index, state) in tx_states.clone() {
-            for index in 1..tx_index {
-                let prev_state = tx_states.get(&index).expect("prev state not found");
-                let mask = check_tx_dependency(prev_state, &state);
-                if mask != 0 {
-                    debug!(
-                        "Transaction {} depends on transaction {} with mask {:x}",
-                        tx_index, index, mask
+        if state_sets.is_empty() {
+            return Err(AnalyzerError::StateSetsNotStored(block_number).into());
+        }
+        let fee_recipient = block
+            .fee_recipient
+            .as_deref()
+            .map(str::parse::<Address>)
+            .transpose()?;
+        let mut gas_used = BTreeMap::new();
+        let mut system_txs = HashSet::new();
+        for tx in self
+            .db
+            .get_transactions_by_block_number(block_number)
+            .await?
+        {
+            gas_used.insert(tx.tx_index, tx.gas_used as u64);
+            if self
+                .block_analyzer
+                .is_system_tx(tx.tx_index, tx.tx_type as u8)
+            {
+                system_txs.insert(tx.tx_index);
+            }
+        }
+        let mut tx_states = BTreeMap::new();
+        let mut pseudo_states = vec![];
+        for row in &state_sets {
+            let state = TransactionStateSet::from(row);
+            // rows without a transaction are the pseudo-transactions
+            if gas_used.contains_key(&row.tx_index) {
+                tx_states.insert(row.tx_index, state);
+            } else {
+                pseudo_states.push((row.tx_index, state));
+            }
+        }
+        let rules = self.block_analyzer.dependency_rules(fee_recipient);
+        let analysis = analyse_block_data(
+            &self
+                .block_analyzer
+                .select_dag_tx_states(&tx_states, &system_txs, pseudo_states),
+            &gas_used,
+            gas_used.len(),
+            &rules,
+            &self.config.simulated_workers,
+        );
+        self.store_analysis(block_number, &rules, &analysis).await
+    }
+
+    /// Re-analyses the stored blocks from `from_block` to `to_block` with
+    /// `reanalyze_stored_block`, skipping those that fail. Returns the number
+    /// of re-analysed blocks.
+    pub async fn reanalyze_stored_range(&self, from_block: i64, to_block: i64) -> Result<usize> {
+        let mut reanalyzed = 0;
+        for block_number in from_block..=to_block {
+            match self.reanalyze_stored_block(block_number).await {
+                Ok(_) => {
+                    info!("Block {} re-analysed from the database", block_number);
+                    reanalyzed += 1;
+                }
+                Err(e) => {
+                    metrics::record_error(&e);
+                    let kind = analyzer_error(&e).map_or("other", AnalyzerError::kind);
+                    warn!(
+                        "Could not re-analyse block {} from the database with {}: {:?}",
+                        block_number, kind, e
                     );
-                    let data = TransactionDag {
-                        block_number,
-                        source_tx: tx_index,
-                        target_tx: index,
-                        dep_type: mask,
-                        created_at: None,
-                        updated_at: None,
-                    };
-                    debug!("dag: {:?}", (tx_index, index));
-                    self.db.insert_transaction_dag(&data).await?;
                 }
             }
         }
+        Ok(reanalyzed)
+    }
+
+    pub async fn update_analyzer_state(
+        &self,
+        block_number: i64,
+        latest_block_number: i64,
+    ) -> Result<()> {
         let mut parallel_analyzer_state = self
             .db
             .get_parallel_analyzer_state_by_chainid(self.chain_id)
             .await?
-            .expect("parallel analyzer state not found");
+            .ok_or(AnalyzerError::AnalyzerStateNotFound(self.chain_id))?;
         parallel_analyzer_state.latest_analyzed_block = block_number;
         parallel_analyzer_state.latest_block = latest_block_number;
+        parallel_analyzer_state.horizon = self.config.analysis_horizon.to_string();
         self.db
             .update_parallel_analyzer_state_by_chainid(&parallel_analyzer_state)
             .await?;
         Ok(())
     }
 
+    /// Highest block the analyzer may analyze under `analysis_horizon`.
+    pub async fn get_horizon_block_number(&self) -> Result<i64> {
+        let tag = match self.config.analysis_horizon {
+            AnalysisHorizon::Latest => BlockNumberOrTag::Latest,
+            AnalysisHorizon::Safe => BlockNumberOrTag::Safe,
+            AnalysisHorizon::Finalized => BlockNumberOrTag::Finalized,
+            AnalysisHorizon::LatestMinus(n) => {
+                let latest = self.execution_api_client.get_block_number().await?;
+                return Ok(latest.saturating_sub(n) as i64);
+            }
+        };
+        let block = self
+            .execution_api_client
+            .get_block_by_number(tag)
+            .await?
+            .ok_or_else(|| eyre!("{} block not found", tag))?;
+        Ok(block.header.number as i64)
+    }
+
+    /// Hash of the canonical block at `block_number`.
+    pub async fn get_block_hash(&self, block_number: i64) -> Result<B256> {
+        let block = self
+            .execution_api_client
+            .get_block_by_number(BlockNumberOrTag::Number(block_number as u64))
+            .await?
+            .ok_or(AnalyzerError::BlockNotFound(block_number))?;
+        Ok(block.header.hash)
+    }
+
+    /// Walks back from `block_number` through the stored blocks to the
+    /// highest one still canonical, or to below the lowest stored block.
+    /// Heights left unstored, by a skipped failed block or a backfill still
+    /// running, are stepped over.
+    pub async fn find_fork_block(&self, block_number: i64) -> Result<i64> {
+        let mut fork_block = block_number;
+        while let Some(stored) = self.db.get_highest_block_up_to(fork_block).await? {
+            if stored.block_hash == self.get_block_hash(stored.block_number).await?.to_string() {
+                return Ok(stored.block_number);
+            }
+            if block_number - stored.block_number >= self.config.max_reorg_depth {
+                return Err(AnalyzerError::ReorgTooDeep {
+                    block_number,
+                    max_depth: self.config.max_reorg_depth,
+                }
+                .into());
+            }
+            fork_block = stored.block_number - 1;
+        }
+        Ok(fork_block)
+    }
+
+    /// Checks the fetched block against the stored chain. If the stored
+    /// parent, or a stored block at the same height, is not on the canonical
+    /// branch, deletes every stored block above the fork point, records the
+    /// reorg and returns the fork block to resume after.
+    pub async fn detect_reorg(&self, data: &BlockData) -> Result<Option<i64>> {
+        let header = &data.block.header;
+        let block_number = header.number as i64;
+        let parent_matches = match self.db.get_block_by_number(block_number - 1).await? {
+            Some(parent) => parent.block_hash == header.parent_hash.to_string(),
+            None => true,
+        };
+        let block_matches = match self.db.get_block_by_number(block_number).await? {
+            Some(block) => block.block_hash == header.hash.to_string(),
+            None => true,
+        };
+        if parent_matches && block_matches {
+            return Ok(None);
+        }
+        let fork_block = self.find_fork_block(block_number - 1).await?;
+        let old_block_hash = self
+            .db
+            .get_block_by_number(fork_block + 1)
+            .await?
+            .map(|block| block.block_hash)
+            .unwrap_or_default();
+        let new_block_hash = self.get_block_hash(fork_block + 1).await?.to_string();
+        // the delete, the reorg row and the state rollback commit together, so
+        // a failure leaves the orphaned parent in place for the retry to see
+        let orphaned_blocks = self
+            .db
+            .roll_back_reorg(&Reorg {
+                id: None,
+                chain_id: self.chain_id,
+                fork_block,
+                orphaned_blocks: 0,
+                old_block_hash,
+                new_block_hash,
+                created_at: None,
+            })
+            .await?;
+        warn!(
+            "Reorg at block {}: {} stored blocks above fork block {} deleted",
+            block_number, orphaned_blocks, fork_block
+        );
+        Ok(Some(fork_block))
+    }
+
+    /// Stores a fetched block and advances the analyzer state to it.
+    pub async fn analyse_block(&self, data: &BlockData, latest_block_number: i64) -> Result<()> {
+        self.store_block_data(data).await?;
+        self.update_analyzer_state(data.block.header.number as i64, latest_block_number)
+            .await
+    }
+
+    /// Delay before retry `attempt`, doubling from `retry.initial_backoff_ms`
+    /// up to `retry.max_backoff_ms`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let retry = &self.config.retry;
+        let backoff = retry
+            .initial_backoff_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(32));
+        Duration::from_millis(backoff.min(retry.max_backoff_ms))
+    }
+
+    /// Checks a fetched block for a reorg and analyses it. Returns the fork
+    /// block if the stored chain was rolled back instead.
+    async fn process_block(&self, data: Result<BlockData>, cursor: Cursor) -> Result<Option<i64>> {
+        let data = data?;
+        match cursor {
+            Cursor::Head {
+                latest_block_number,
+            } => {
+                if let Some(fork_block) = self.detect_reorg(&data).await? {
+                    return Ok(Some(fork_block));
+                }
+                self.analyse_block(&data, latest_block_number).await?;
+            }
+            // ranges sit below the head and are not tracked in the analyzer state
+            Cursor::Backfill => self.store_block_data(&data).await?,
+        }
+        info!("Block {} analysed successfully", data.block.header.number);
+        Ok(None)
+    }
+
+    /// Processes a block, refetching and retrying it with exponential backoff
+    /// while it fails with a retryable error. A block that still fails is
+    /// recorded in `failed_blocks` and skipped; failing to record it, or an
+    /// error that stops the head follower, is returned.
+    async fn process_block_with_retries(
+        &self,
+        block_number: i64,
+        data: Result<BlockData>,
+        cursor: Cursor,
+    ) -> Result<Option<i64>> {
+        let mut data = data;
+        let mut attempts = 1;
+        loop {
+            let e = match self.process_block(data, cursor).await {
+                Ok(fork_block) => return Ok(fork_block),
+                Err(e) => e,
+            };
+            metrics::record_error(&e);
+            let class = classify_error(&e);
+            let kind = analyzer_error(&e).map_or("other", AnalyzerError::kind);
+            if analyzer_error(&e).is_some_and(AnalyzerError::stops_follower) {
+                return Err(e);
+            }
+            if class.is_retryable() && attempts < self.config.retry.max_attempts {
+                let backoff = self.backoff(attempts);
+                warn!(
+                    "Attempt {} at block {} failed with {} ({}), retrying in {:?}: {:?}",
+                    attempts, block_number, kind, class, backoff, e
+                );
+                tokio::time::sleep(backoff).await;
+                attempts += 1;
+                data = self.fetch_block_data(block_number).await;
+                continue;
+            }
+            error!(
+                "Skipping block {} after {} attempts with {} ({}): {:?}",
+                block_number, attempts, kind, class, e
+            );
+            self.record_failed_block(block_number, class, attempts, &e)
+                .await?;
+            if let Cursor::Head {
+                latest_block_number,
+            } = cursor
+            {
+                self.update_analyzer_state(block_number, latest_block_number)
+                    .await?;
+            }
+            return Ok(None);
+        }
+    }
+
+    pub async fn record_failed_block(
+        &self,
+        block_number: i64,
+        class: ErrorClass,
+        attempts: u32,
+        e: &eyre::Report,
+    ) -> Result<()> {
+        self.db
+            .upsert_failed_block(&FailedBlock {
+                chain_id: self.chain_id,
+                block_number,
+                error_class: class.to_string(),
+                error: format!("{e:#}"),
+                attempts: attempts as i64,
+                created_at: None,
+                updated_at: None,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Analyses the blocks from `from_block` to `to_block`, skipping those
+    /// already analyzed with the current `DAG_VERSION` unless `reanalyze` is
+    /// set. Existing DAG rows of an analysed block are replaced. Returns the
+    /// number of blocks processed, failures being recorded in `failed_blocks`.
+    pub async fn analyze_range(
+        &self,
+        from_block: i64,
+        to_block: i64,
+        reanalyze: bool,
+    ) -> Result<usize> {
+        let mut block_numbers = vec![];
+        for block_number in from_block..=to_block {
+            let analyzed = self
+                .db
+                .get_block_by_number(block_number)
+                .await?
+                .is_some_and(|block| block.dag_version == DAG_VERSION);
+            if reanalyze || !analyzed {
+                block_numbers.push(block_number);
+            }
+        }
+        let processed = block_numbers.len();
+        let mut blocks = self.prefetch_blocks(block_numbers);
+        while let Some((number, data)) = blocks.next().await {
+            self.process_block_with_retries(number, data, Cursor::Backfill)
+                .await?;
+        }
+        Ok(processed)
+    }
+
+    /// Re-analyses blocks from `failed_blocks` once each, removing those that
+    /// succeed and recording the attempt for the others. Returns the number of
+    /// recovered blocks.
+    pub async fn retry_failed_blocks(&self, block_numbers: &[i64]) -> Result<usize> {
+        let mut recovered = 0;
+        for &block_number in block_numbers {
+            let result = match self.fetch_block_data(block_number).await {
+                Ok(data) => self.store_block_data(&data).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => {
+                    info!("Failed block {} analysed successfully", block_number);
+                    self.db
+                        .delete_failed_block(self.chain_id, block_number)
+                        .await?;
+                    recovered += 1;
+                }
+                Err(e) => {
+                    metrics::record_error(&e);
+                    let class = classify_error(&e);
+                    let kind = analyzer_error(&e).map_or("other", AnalyzerError::kind);
+                    warn!(
+                        "Failed block {} failed again with {} ({}): {:?}",
+                        block_number, kind, class, e
+                    );
+                    self.record_failed_block(block_number, class, 1, &e).await?;
+                }
+            }
+        }
+        Ok(recovered)
+    }
+
     pub async fn run(&self) -> Result<()> {
+        info!(
+            "Using chain profile {} for chain {}, following the {} head",
+            self.block_analyzer.chain_profile.name, self.chain_id, self.config.analysis_horizon
+        );
         let mut block_number = self.start_block;
         loop {
-            let latest_block_number = self.execution_api_client.get_block_number().await? as i64;
+            let latest_block_number = match self.get_horizon_block_number().await {
+                Ok(latest_block_number) => latest_block_number,
+                Err(e) => {
+                    metrics::record_error(&e);
+                    warn!(
+                        "Could not read the {} head: {:?}",
+                        self.config.analysis_horizon, e
+                    );
+                    tokio::time::sleep(self.backoff(1)).await;
+                    continue;
+                }
+            };
             info!(
                 "Analysing block {}, latest_block: {}",
                 block_number, latest_block_number
             );
             if block_number > latest_block_number {
-                tokio::time::sleep(tokio::time::Duration::from_secs(12)).await;
+                tokio::time::sleep(tokio::time::Duration::from_millis(
+                    self.block_analyzer.chain_profile.block_time_ms,
+                ))
+                .await;
                 continue;
             }
-            match self.analyse_block(block_number, latest_block_number).await {
-                Ok(_) => {
-                    info!("Block {} analysed successfully", block_number);
-                    block_number += 1;
-                }
-                Err(e) => {
-                    error!("Error analysing block {}: {:?}", block_number, e);
-                    break;
+            let mut blocks = self.prefetch_blocks(block_number..=latest_block_number);
+            while let Some((number, data)) = blocks.next().await {
+                let cursor = Cursor::Head {
+                    latest_block_number,
+                };
+                match self.process_block_with_retries(number, data, cursor).await {
+                    // re-analyze the canonical branch from the fork block
+                    Ok(Some(fork_block)) => {
+                        block_number = fork_block + 1;
+                        break;
+                    }
+                    Ok(None) => block_number = number + 1,
+                    Err(e) if analyzer_error(&e).is_some_and(AnalyzerError::stops_follower) => {
+                        error!("Stopping the head follower at block {}: {:?}", number, e);
+                        return Err(e);
+                    }
+                    // the failure could not be recorded, start over from this block
+                    Err(e) => {
+                        error!("Error analysing block {}: {:?}", number, e);
+                        tokio::time::sleep(self.backoff(self.config.retry.max_attempts)).await;
+                        break;
+                    }
                 }
             }
         }
+    }
+
+    /// Splits the blocks from `backfill.from_block` up to the head follower's
+    /// first block into `analysis_ranges`, skipping those already recorded.
+    pub async fn seed_backfill_ranges(&self) -> Result<()> {
+        let backfill = &self.config.backfill;
+        let state = self
+            .db
+            .get_parallel_analyzer_state_by_chainid(self.chain_id)
+            .await?
+            .ok_or(AnalyzerError::AnalyzerStateNotFound(self.chain_id))?;
+        let queued = queue_uncovered_ranges(
+            &self.db,
+            self.chain_id,
+            backfill,
+            backfill.from_block,
+            state.start_block - 1,
+        )
+        .await?;
+        if queued > 0 {
+            info!(
+                "Backfilling {} new ranges of up to {} blocks",
+                queued,
+                backfill.range_size.max(1)
+            );
+        }
+        Ok(())
+    }
+
+    /// Analyses the remaining blocks of a claimed range in its direction,
+    /// recording progress after every block.
+    async fn backfill_range(&self, range: &AnalysisRange) -> Result<()> {
+        let id = range.id.unwrap_or_default();
+        let forward = range.direction == BackfillDirection::Forward.as_str();
+        let block_numbers: Vec<i64> = if forward {
+            (range.next_block..=range.end_block).collect()
+        } else {
+            (range.start_block..=range.next_block).rev().collect()
+        };
+        let mut blocks = self.prefetch_blocks(block_numbers);
+        while let Some((number, data)) = blocks.next().await {
+            self.process_block_with_retries(number, data, Cursor::Backfill)
+                .await?;
+            let next_block = if forward { number + 1 } else { number - 1 };
+            self.db
+                .update_analysis_range(id, next_block, RANGE_RUNNING)
+                .await?;
+        }
+        let next_block = if forward {
+            range.end_block + 1
+        } else {
+            range.start_block - 1
+        };
+        self.db
+            .update_analysis_range(id, next_block, RANGE_DONE)
+            .await?;
+        Ok(())
+    }
+
+    /// Claims and analyses ranges until none is left.
+    pub async fn run_backfill_worker(&self, worker: usize) -> Result<()> {
+        let backfill = &self.config.backfill;
+        let worker = format!("{}-{}", std::process::id(), worker);
+        loop {
+            let Some(range) = self
+                .db
+                .claim_analysis_range(
+                    self.chain_id,
+                    &worker,
+                    backfill.direction == BackfillDirection::Backward,
+                    backfill.stale_range_secs,
+                )
+                .await?
+            else {
+                info!("Backfill worker {} found no ranges left", worker);
+                return Ok(());
+            };
+            info!(
+                "Backfill worker {} claimed blocks {} to {}",
+                worker, range.start_block, range.end_block
+            );
+            if let Err(e) = self.backfill_range(&range).await {
+                // the range stays running and is claimed again once stale
+                error!(
+                    "Backfill worker {} failed in blocks {} to {}: {:?}",
+                    worker, range.start_block, range.end_block, e
+                );
+                tokio::time::sleep(self.backoff(self.config.retry.max_attempts)).await;
+            }
+        }
+    }
+
+    /// Seeds the backfill ranges and runs `backfill.workers` workers on them.
+    pub async fn run_backfill(&self) -> Result<()> {
+        if self.config.backfill.workers == 0 {
+            return Ok(());
+        }
+        if let Err(e) = self.seed_backfill_ranges().await {
+            error!("Could not seed the backfill ranges: {:?}", e);
+            return Err(e);
+        }
+        let workers = (0..self.config.backfill.workers).map(|worker| async move {
+            if let Err(e) = self.run_backfill_worker(worker).await {
+                error!("Backfill worker {} stopped: {:?}", worker, e);
+            }
+        });
+        future::join_all(workers).await;
         Ok(())
     }
 }
 
+/// Pending backfill ranges of up to `backfill.range_size` blocks covering
+/// `from_block` to `to_block`.
+pub fn analysis_ranges(
+    chain_id: i64,
+    backfill: &BackfillConfig,
+    from_block: i64,
+    to_block: i64,
+) -> Vec<AnalysisRange> {
+    let range_size = backfill.range_size.max(1);
+    (from_block..=to_block)
+        .step_by(range_size as usize)
+        .map(|start_block| {
+            let end_block = (start_block + range_size - 1).min(to_block);
+            AnalysisRange {
+                id: None,
+                chain_id,
+                start_block,
+                end_block,
+                direction: backfill.direction.as_str().to_string(),
+                next_block: match backfill.direction {
+                    BackfillDirection::Backward => end_block,
+                    BackfillDirection::Forward => start_block,
+                },
+                status: RANGE_PENDING.to_string(),
+                worker: None,
+                created_at: None,
+                updated_at: None,
+            }
+        })
+        .collect()
+}
+
+/// Spans of `from_block` to `to_block` that none of `ranges` cover.
+pub fn uncovered_spans(
+    from_block: i64,
+    to_block: i64,
+    ranges: &[AnalysisRange],
+) -> Vec<(i64, i64)> {
+    let mut covered: Vec<(i64, i64)> = ranges
+        .iter()
+        .map(|range| (range.start_block, range.end_block))
+        .collect();
+    covered.sort_unstable();
+    let mut spans = Vec::new();
+    let mut next_block = from_block;
+    for (start_block, end_block) in covered {
+        if next_block > to_block {
+            break;
+        }
+        if start_block > next_block {
+            spans.push((next_block, (start_block - 1).min(to_block)));
+        }
+        next_block = next_block.max(end_block + 1);
+    }
+    if next_block <= to_block {
+        spans.push((next_block, to_block));
+    }
+    spans
+}
+
+/// Queues the blocks from `from_block` to `to_block` not covered by a
+/// recorded range as `analysis_ranges`. Returns the number of new ranges.
+pub async fn queue_uncovered_ranges(
+    db: &DB,
+    chain_id: i64,
+    backfill: &BackfillConfig,
+    from_block: i64,
+    to_block: i64,
+) -> Result<usize, sqlx::Error> {
+    let recorded = db.get_analysis_ranges_by_chainid(chain_id).await?;
+    let ranges: Vec<AnalysisRange> = uncovered_spans(from_block, to_block, &recorded)
+        .into_iter()
+        .flat_map(|(from_block, to_block)| {
+            analysis_ranges(chain_id, backfill, from_block, to_block)
+        })
+        .collect();
+    if !ranges.is_empty() {
+        db.insert_analysis_ranges(&ranges).await?;
+    }
+    Ok(ranges.len())
+}
+
+/// Returns the full transactions of a block fetched with `.full()`.
+pub fn block_transactions(block: &AlloyBlock) -> Result<Vec<AlloyTransaction>, AnalyzerError> {
+    block
+        .transactions
+        .as_transactions()
+        .map(<[_]>::to_vec)
+        .ok_or(AnalyzerError::TransactionsNotFull(
+            block.header.number as i64,
+        ))
+}
+
+/// Index of a mined transaction within its block.
+pub fn tx_index(tx: &AlloyTransaction) -> Result<i64, AnalyzerError> {
+    tx.transaction_index
+        .map(|tx_index| tx_index as i64)
+        .ok_or(AnalyzerError::MissingTransactionIndex(tx.tx_hash()))
+}
+
+fn prestate_tracing_options(diff_mode: bool) -> GethDebugTracingOptions {
+    GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+        diff_mode: Some(diff_mode),
+        ..Default::default()
+    })
+}
+
+/// Unwraps one entry of a block trace, checking it belongs to `tx_hash`.
+fn trace_result_for_tx(result: TraceResult, tx_hash: TxHash) -> Result<GethTrace, AnalyzerError> {
+    if let Some(traced_hash) = result.tx_hash() {
+        if traced_hash != tx_hash {
+            return Err(AnalyzerError::TraceMismatch {
+                expected: tx_hash,
+                traced: traced_hash,
+            });
+        }
+    }
+    match result {
+        TraceResult::Success { result, .. } => Ok(result),
+        TraceResult::Error { error, .. } => Err(AnalyzerError::TraceFailed { tx_hash, error }),
+    }
+}
+
+/// Read/write sets of every transaction of a block, from the prestate and
+/// diff-mode results of `debug_traceBlockByNumber`.
+pub fn block_traces_to_states(
+    block_number: i64,
+    transactions: &[AlloyTransaction],
+    read_traces: Vec<TraceResult>,
+    write_traces: Vec<TraceResult>,
+) -> Result<BTreeMap<i64, TransactionStateSet>, AnalyzerError> {
+    for traces in [&read_traces, &write_traces] {
+        if traces.len() != transactions.len() {
+            return Err(AnalyzerError::TraceCountMismatch {
+                block_number,
+                transactions: transactions.len(),
+                traces: traces.len(),
+            });
+        }
+    }
+    let mut tx_states = BTreeMap::new();
+    for ((tx, read_trace), write_trace) in transactions.iter().zip(read_traces).zip(write_traces) {
+        let tx_hash = tx.tx_hash();
+        let read_trace = trace_result_for_tx(read_trace, tx_hash)?;
+        let write_trace = trace_result_for_tx(write_trace, tx_hash)?;
+        tx_states.insert(
+            tx_index(tx)?,
+            traces_to_state_set(
+                tx_hash,
+                read_trace,
+                write_trace,
+                tx.from(),
+                &tx_authorities(tx),
+            )?,
+        );
+    }
+    Ok(tx_states)
+}
+
+/// EIP-7702 authorities of a set-code transaction, recovered from its
+/// authorization list. Authorizations with an invalid signature are skipped.
+pub fn tx_authorities(tx: &AlloyTransaction) -> Vec<Address> {
+    tx.authorization_list()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|authorization| authorization.recover_authority().ok())
+        .collect()
+}
+
+/// Builds the read set from a prestate trace and the write set from a
+/// diff-mode prestate trace of the same transaction.
+///
+/// The prestate trace carries the nonce of every touched account, so the
+/// nonce read set is narrowed to the sender and the accounts whose nonce the
+/// transaction changed.
+///
+/// An authorization is applied only when the authority's nonce or code
+/// changes in the diff trace; invalid or stale authorizations are skipped by
+/// the EVM and left out. Each applied authority has its code and nonce read
+/// and written, even when re-delegating leaves its code unchanged.
+pub fn traces_to_state_set(
+    tx_hash: TxHash,
+    read_trace: GethTrace,
+    write_trace: GethTrace,
+    sender: Address,
+    authorities: &[Address],
+) -> Result<TransactionStateSet, AnalyzerError> {
+    let unexpected = |expected| AnalyzerError::UnexpectedTraceFrame { tx_hash, expected };
+    let read_state = read_trace
+        .try_into_pre_state_frame()
+        .ok()
+        .and_then(|frame| frame.as_default().cloned())
+        .ok_or_else(|| unexpected("prestate"))?
+        .0;
+    let write_state = write_trace
+        .try_into_pre_state_frame()
+        .ok()
+        .and_then(|frame| frame.as_diff().cloned())
+        .ok_or_else(|| unexpected("prestate diff"))?
+        .post;
+    let mut read_set = account_state_to_set(read_state);
+    let mut write_set = account_state_to_set(write_state);
+    read_set.nonce_set = write_set.nonce_set.clone();
+    read_set.nonce_set.insert(sender);
+    let applied: HashSet<Address> = authorities
+        .iter()
+        .copied()
+        .filter(|authority| {
+            write_set.nonce_set.contains(authority) || write_set.code_set.contains(authority)
+        })
+        .collect();
+    for authority in &applied {
+        read_set.code_set.insert(*authority);
+        read_set.nonce_set.insert(*authority);
+        write_set.code_set.insert(*authority);
+        write_set.nonce_set.insert(*authority);
+    }
+    Ok(TransactionStateSet {
+        read_set,
+        write_set,
+        authorities: applied,
+    })
+}
+
 pub fn account_state_to_set(account_state: BTreeMap<Address, AccountState>) -> StateSet {
     let mut balance_set = HashSet::new();
     let mut code_set = HashSet::new();
     let mut storage_set = HashSet::new();
+    let mut nonce_set = HashSet::new();
     for (address, state) in account_state {
         if state.balance.is_some() {
             balance_set.insert(address);
@@ -246,49 +1433,1335 @@ pub fn account_state_to_set(account_state: BTreeMap<Address, AccountState>) -> S
         if state.code.is_some() {
             code_set.insert(address);
         }
+        if state.nonce.is_some() {
+            nonce_set.insert(address);
+        }
         for (key, _) in state.storage {
-            storage_set.insert(key);
+            storage_set.insert((address, key));
         }
     }
     StateSet {
         balance_set,
         code_set,
         storage_set,
+        nonce_set,
     }
 }
 
-pub fn check_tx_dependency(prev_state: &TransactionStateSet, state: &TransactionStateSet) -> i16 {
+/// Builds the DAG of a block with `tx_count` transactions under `rules`,
+/// computes its metrics, and simulates it on each of the `workers` counts.
+pub fn analyse_block_data(
+    tx_states: &BTreeMap<i64, TransactionStateSet>,
+    gas_used: &BTreeMap<i64, u64>,
+    tx_count: usize,
+    rules: &DependencyRules,
+    workers: &[usize],
+) -> BlockAnalysis {
+    let nodes: Vec<(i64, u64)> = tx_states
+        .keys()
+        .map(|tx_index| {
+            (
+                *tx_index,
+                gas_used.get(tx_index).copied().unwrap_or_default(),
+            )
+        })
+        .collect();
+    let pseudo_txs: BTreeSet<i64> = tx_states
+        .keys()
+        .copied()
+        .filter(|tx_index| pseudo_transaction_name(*tx_index, tx_count).is_some())
+        .collect();
+    let edges = build_transaction_dag(tx_states, rules);
+    let metrics = dag_metrics(&nodes, &pseudo_txs, &edges);
+    let naive_metrics = if rules.balance_exclusions.is_empty() {
+        metrics.clone()
+    } else {
+        let naive_rules = DependencyRules {
+            balance_exclusions: HashSet::new(),
+            ..rules.clone()
+        };
+        dag_metrics(
+            &nodes,
+            &pseudo_txs,
+            &build_transaction_dag(tx_states, &naive_rules),
+        )
+    };
+    BlockAnalysis {
+        schedules: simulate_schedules(&nodes, &edges, workers),
+        stm_sims: simulate_block_stm(tx_states, gas_used, rules, workers),
+        edges,
+        metrics,
+        naive_metrics,
+    }
+}
+
+/// Adjustments to dependency detection.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyRules {
+    /// system accounts whose state never causes a dependency
+    pub ignored_addresses: HashSet<Address>,
+    /// accounts whose balance never causes a dependency, as with lazy fee
+    /// accounting
+    pub balance_exclusions: HashSet<Address>,
+}
+
+impl DependencyRules {
+    pub fn ignores(&self, key: &StateKey) -> bool {
+        if self.ignored_addresses.contains(&key.address()) {
+            return true;
+        }
+        match key {
+            StateKey::Balance(address) => self.balance_exclusions.contains(address),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the hazard bits (`HAZARD_*`) between an earlier and a later
+/// transaction's accesses to one kind of state.
+fn hazard_mask<T: Eq + Hash>(
+    prev_read: &HashSet<T>,
+    prev_write: &HashSet<T>,
+    read: &HashSet<T>,
+    write: &HashSet<T>,
+    to_key: impl Fn(&T) -> StateKey,
+    rules: &DependencyRules,
+) -> i16 {
+    let conflicting = |earlier: &HashSet<T>, later: &HashSet<T>| {
+        earlier
+            .intersection(later)
+            .any(|item| !rules.ignores(&to_key(item)))
+    };
     let mut mask = 0;
-    // check balance dependency
-    if prev_state
-        .write_set
-        .balance_set
-        .intersection(&state.read_set.balance_set)
-        .count()
-        > 0
-    {
-        mask |= 0x1;
+    // read-after-write
+    if conflicting(prev_write, read) {
+        mask |= HAZARD_RAW;
+    }
+    // write-after-write
+    if conflicting(prev_write, write) {
+        mask |= HAZARD_WAW;
     }
+    // write-after-read
+    if conflicting(prev_read, write) {
+        mask |= HAZARD_WAR;
+    }
+    mask
+}
 
-    // check code dependency
-    if prev_state
-        .write_set
-        .code_set
-        .intersection(&state.read_set.code_set)
-        .count()
-        > 0
-    {
-        mask |= 0x10;
+/// Collects the keys behind each hazard between an earlier and a later
+/// transaction's accesses to one kind of state.
+fn hazard_conflicts<T: Eq + Hash>(
+    prev_read: &HashSet<T>,
+    prev_write: &HashSet<T>,
+    read: &HashSet<T>,
+    write: &HashSet<T>,
+    to_key: impl Fn(&T) -> StateKey,
+    rules: &DependencyRules,
+    conflicts: &mut Vec<Conflict>,
+) {
+    for (hazard, earlier, later) in [
+        (HAZARD_RAW, prev_write, read),
+        (HAZARD_WAW, prev_write, write),
+        (HAZARD_WAR, prev_read, write),
+    ] {
+        conflicts.extend(
+            earlier
+                .intersection(later)
+                .map(&to_key)
+                .filter(|key| !rules.ignores(key))
+                .map(|key| Conflict {
+                    key,
+                    hazard,
+                    delegation: false,
+                }),
+        );
     }
-    // check storage dependency
-    if prev_state
-        .write_set
-        .storage_set
-        .intersection(&state.read_set.storage_set)
-        .count()
-        > 0
-    {
-        mask |= 0x100;
+}
+
+/// Lists the conflicting keys behind `check_tx_dependency`.
+pub fn dependency_conflicts(
+    prev_state: &TransactionStateSet,
+    state: &TransactionStateSet,
+    rules: &DependencyRules,
+) -> Vec<Conflict> {
+    let (prev_read, prev_write) = (&prev_state.read_set, &prev_state.write_set);
+    let (read, write) = (&state.read_set, &state.write_set);
+    let mut conflicts = Vec::new();
+    hazard_conflicts(
+        &prev_read.balance_set,
+        &prev_write.balance_set,
+        &read.balance_set,
+        &write.balance_set,
+        |address| StateKey::Balance(*address),
+        rules,
+        &mut conflicts,
+    );
+    hazard_conflicts(
+        &prev_read.code_set,
+        &prev_write.code_set,
+        &read.code_set,
+        &write.code_set,
+        |address| StateKey::Code(*address),
+        rules,
+        &mut conflicts,
+    );
+    hazard_conflicts(
+        &prev_read.storage_set,
+        &prev_write.storage_set,
+        &read.storage_set,
+        &write.storage_set,
+        |(address, slot)| StateKey::Storage(*address, *slot),
+        rules,
+        &mut conflicts,
+    );
+    hazard_conflicts(
+        &prev_read.nonce_set,
+        &prev_write.nonce_set,
+        &read.nonce_set,
+        &write.nonce_set,
+        |address| StateKey::Nonce(*address),
+        rules,
+        &mut conflicts,
+    );
+    for conflict in &mut conflicts {
+        if let StateKey::Code(address) = conflict.key {
+            conflict.delegation =
+                prev_state.authorities.contains(&address) || state.authorities.contains(&address);
+        }
     }
+    conflicts.sort();
+    conflicts
+}
+
+pub fn check_tx_dependency(
+    prev_state: &TransactionStateSet,
+    state: &TransactionStateSet,
+    rules: &DependencyRules,
+) -> i16 {
+    let (prev_read, prev_write) = (&prev_state.read_set, &prev_state.write_set);
+    let (read, write) = (&state.read_set, &state.write_set);
+    let mut mask = 0;
+    // check balance dependency
+    mask |= DEP_BALANCE
+        * hazard_mask(
+            &prev_read.balance_set,
+            &prev_write.balance_set,
+            &read.balance_set,
+            &write.balance_set,
+            |address| StateKey::Balance(*address),
+            rules,
+        );
+    // check code dependency
+    mask |= DEP_CODE
+        * hazard_mask(
+            &prev_read.code_set,
+            &prev_write.code_set,
+            &read.code_set,
+            &write.code_set,
+            |address| StateKey::Code(*address),
+            rules,
+        );
+    // check storage dependency
+    mask |= DEP_STORAGE
+        * hazard_mask(
+            &prev_read.storage_set,
+            &prev_write.storage_set,
+            &read.storage_set,
+            &write.storage_set,
+            |(address, slot)| StateKey::Storage(*address, *slot),
+            rules,
+        );
+    // check nonce dependency
+    mask |= DEP_NONCE
+        * hazard_mask(
+            &prev_read.nonce_set,
+            &prev_write.nonce_set,
+            &read.nonce_set,
+            &write.nonce_set,
+            |address| StateKey::Nonce(*address),
+            rules,
+        );
     mask
 }
+
+/// Checks every transaction against all transactions before it in the block.
+pub fn build_transaction_dag(
+    tx_states: &BTreeMap<i64, TransactionStateSet>,
+    rules: &DependencyRules,
+) -> Vec<DagEdge> {
+    let mut edges = Vec::new();
+    for (tx_index, state) in tx_states {
+        for (index, prev_state) in tx_states.range(..tx_index) {
+            let mask = check_tx_dependency(prev_state, state, rules);
+            if mask != 0 {
+                edges.push(DagEdge {
+                    source: *tx_index,
+                    target: *index,
+                    dep_type: mask,
+                    conflicts: dependency_conflicts(prev_state, state, rules),
+                });
+            }
+        }
+    }
+    edges
+}
+
+pub fn hazard_name(hazard: i16) -> &'static str {
+    match hazard {
+        HAZARD_RAW => "raw",
+        HAZARD_WAW => "waw",
+        HAZARD_WAR => "war",
+        _ => "unknown",
+    }
+}
+
+/// Names of the resources with a hazard in a `dep_type` mask.
+pub fn dep_type_resources(dep_type: i16) -> Vec<&'static str> {
+    DEP_RESOURCES
+        .into_iter()
+        .filter(|(resource, _)| (dep_type / resource) & 0xf != 0)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Names of the hazard classes present in a `dep_type` mask, across all
+/// resources.
+pub fn dep_type_hazards(dep_type: i16) -> Vec<&'static str> {
+    let hazards = DEP_RESOURCES
+        .into_iter()
+        .map(|(resource, _)| resource)
+        .fold(0, |acc, resource| acc | (dep_type / resource) & 0xf);
+    [HAZARD_RAW, HAZARD_WAW, HAZARD_WAR]
+        .into_iter()
+        .filter(|hazard| hazards & hazard != 0)
+        .map(hazard_name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        task::{Context, Poll},
+    };
+
+    use alloy::{
+        primitives::{U256, address, b256},
+        rpc::{
+            json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload},
+            types::trace::geth::{DiffMode, PreStateFrame, PreStateMode},
+        },
+        transports::{TransportError, TransportFut},
+    };
+    use serde_json::{Value, json, value::RawValue};
+    use sqlx::PgPool;
+    use tower::Service;
+
+    use super::*;
+    use crate::{
+        analyze_file::RecordedBlock,
+        chain_profile::{BEACON_ROOTS, HISTORY_STORAGE},
+        db::{
+            block_parallelism_stats::BlockParallelismStatsDB,
+            parallel_analyzer_state::ParallelAnalyzerState, transaction_dag::TransactionDagDB,
+        },
+        pseudo_transactions::withdrawals_state,
+    };
+
+    /// Config with the required settings, followed by `extra`.
+    fn test_config(extra: &str) -> Config {
+        config::Config::builder()
+            .add_source(config::File::from_str(
+                &format!(
+                    r#"
+                    execution_api = "http://127.0.0.1:1"
+                    start_block = 10
+                    chain_id = 1
+                    server_addr = "127.0.0.1:0"
+                    database_url = "postgres://localhost/unused"
+                    {extra}
+                    "#
+                ),
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    /// A database the test never reaches.
+    fn unused_db() -> DB {
+        DB::new(
+            sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy("postgres://localhost/unused")
+                .unwrap(),
+        )
+    }
+
+    /// Answer of the stub node to a method and its params.
+    type Handler = dyn Fn(&str, Value) -> Value + Send + Sync;
+
+    /// Execution api answering every request with its `Handler`.
+    #[derive(Clone)]
+    struct Node(Arc<Handler>);
+
+    impl Service<RequestPacket> for Node {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, packet: RequestPacket) -> Self::Future {
+            let RequestPacket::Single(request) = packet else {
+                unreachable!("the analyzer sends single requests")
+            };
+            let params = request.params().map_or(Value::Null, |params| {
+                serde_json::from_str(params.get()).unwrap()
+            });
+            let result = (self.0)(request.method(), params);
+            Box::pin(async move {
+                Ok(ResponsePacket::Single(Response {
+                    id: request.id().clone(),
+                    payload: ResponsePayload::Success(
+                        RawValue::from_string(result.to_string()).unwrap(),
+                    ),
+                }))
+            })
+        }
+    }
+
+    fn analyzer_with_node(db: DB, config: Config, node: Node) -> ParallelAnalyzer {
+        let mut analyzer = ParallelAnalyzer::new(Arc::new(db), config, 0);
+        analyzer.execution_api_client = Arc::new(RootProvider::new(
+            ClientBuilder::default().transport(node, true),
+        ));
+        analyzer
+    }
+
+    fn recorded_block_json() -> Value {
+        serde_json::from_str(include_str!("../testdata/recorded_block.json")).unwrap()
+    }
+
+    fn state_set(balances: &[Address], storage: &[(Address, B256)]) -> StateSet {
+        StateSet {
+            balance_set: balances.iter().copied().collect(),
+            code_set: HashSet::new(),
+            storage_set: storage.iter().copied().collect(),
+            nonce_set: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn build_transaction_dag_includes_first_transaction() {
+        let oracle = address!("0x00000000000000000000000000000000000000aa");
+        let token = address!("0x00000000000000000000000000000000000000bb");
+        let sender = address!("0x00000000000000000000000000000000000000cc");
+        let price_slot =
+            b256!("0x0000000000000000000000000000000000000000000000000000000000000001");
+        let tx_states = BTreeMap::from([
+            // tx 0 updates the oracle price
+            (
+                0,
+                TransactionStateSet {
+                    read_set: state_set(&[], &[]),
+                    write_set: state_set(&[], &[(oracle, price_slot)]),
+                    authorities: HashSet::new(),
+                },
+            ),
+            // tx 1 reads the price and reads and writes its own token slot
+            (
+                1,
+                TransactionStateSet {
+                    read_set: state_set(&[sender], &[(oracle, price_slot), (token, B256::ZERO)]),
+                    write_set: state_set(&[sender], &[(token, B256::ZERO)]),
+                    authorities: HashSet::new(),
+                },
+            ),
+            // tx 2 touches slot 0 of the oracle, unrelated to slot 0 of the token
+            (
+                2,
+                TransactionStateSet {
+                    read_set: state_set(&[], &[(oracle, B256::ZERO)]),
+                    write_set: state_set(&[], &[]),
+                    authorities: HashSet::new(),
+                },
+            ),
+        ]);
+
+        let edges = build_transaction_dag(&tx_states, &DependencyRules::default());
+
+        assert_eq!(
+            edges,
+            vec![DagEdge {
+                source: 1,
+                target: 0,
+                dep_type: DEP_STORAGE * HAZARD_RAW,
+                conflicts: vec![Conflict {
+                    key: StateKey::Storage(oracle, price_slot),
+                    hazard: HAZARD_RAW,
+                    delegation: false,
+                }],
+            }]
+        );
+        assert!(edges.iter().all(|edge| edge.source != edge.target));
+    }
+
+    #[test]
+    fn build_transaction_dag_records_write_after_write_and_write_after_read() {
+        let pool = address!("0x00000000000000000000000000000000000000aa");
+        let sender = address!("0x00000000000000000000000000000000000000cc");
+        let reserve_slot = B256::ZERO;
+        let fee_slot = b256!("0x0000000000000000000000000000000000000000000000000000000000000001");
+        let tx_states = BTreeMap::from([
+            // tx 0 reads the reserve, writes the fee and pays the sender
+            (
+                0,
+                TransactionStateSet {
+                    read_set: state_set(&[], &[(pool, reserve_slot)]),
+                    write_set: state_set(&[sender], &[(pool, fee_slot)]),
+                    authorities: HashSet::new(),
+                },
+            ),
+            // tx 1 overwrites both slots without reading them, and reads the
+            // sender's balance
+            (
+                1,
+                TransactionStateSet {
+                    read_set: state_set(&[sender], &[]),
+                    write_set: state_set(&[], &[(pool, reserve_slot), (pool, fee_slot)]),
+                    authorities: HashSet::new(),
+                },
+            ),
+        ]);
+
+        let edges = build_transaction_dag(&tx_states, &DependencyRules::default());
+
+        assert_eq!(
+            edges,
+            vec![DagEdge {
+                source: 1,
+                target: 0,
+                dep_type: DEP_BALANCE * HAZARD_RAW + DEP_STORAGE * (HAZARD_WAW | HAZARD_WAR),
+                conflicts: vec![
+                    Conflict {
+                        key: StateKey::Balance(sender),
+                        hazard: HAZARD_RAW,
+                        delegation: false,
+                    },
+                    Conflict {
+                        key: StateKey::Storage(pool, reserve_slot),
+                        hazard: HAZARD_WAR,
+                        delegation: false,
+                    },
+                    Conflict {
+                        key: StateKey::Storage(pool, fee_slot),
+                        hazard: HAZARD_WAW,
+                        delegation: false,
+                    },
+                ],
+            }]
+        );
+        assert_eq!(
+            dep_type_resources(edges[0].dep_type),
+            vec!["balance", "storage"]
+        );
+        assert_eq!(
+            dep_type_hazards(edges[0].dep_type),
+            vec!["raw", "waw", "war"]
+        );
+    }
+
+    fn prestate(accounts: serde_json::Value) -> GethTrace {
+        GethTrace::PreStateTracer(PreStateFrame::Default(PreStateMode(
+            serde_json::from_value(accounts).unwrap(),
+        )))
+    }
+
+    fn prestate_diff(post: serde_json::Value) -> GethTrace {
+        GethTrace::PreStateTracer(PreStateFrame::Diff(DiffMode {
+            pre: BTreeMap::new(),
+            post: serde_json::from_value(post).unwrap(),
+        }))
+    }
+
+    #[test]
+    fn nonce_dependencies_only_follow_changed_nonces() {
+        let sender = address!("0x00000000000000000000000000000000000000a0");
+        let other = address!("0x00000000000000000000000000000000000000a1");
+        let contract = address!("0x00000000000000000000000000000000000000cc");
+        let state = |sender, pre, post| {
+            traces_to_state_set(
+                TxHash::ZERO,
+                prestate(pre),
+                prestate_diff(post),
+                sender,
+                &[],
+            )
+            .unwrap()
+        };
+        let tx_states = BTreeMap::from([
+            // two transactions of the same sender
+            (
+                0,
+                state(
+                    sender,
+                    json!({ sender.to_string(): { "nonce": 0 }, contract.to_string(): { "nonce": 1 } }),
+                    json!({ sender.to_string(): { "nonce": 1 } }),
+                ),
+            ),
+            (
+                1,
+                state(
+                    sender,
+                    json!({ sender.to_string(): { "nonce": 1 } }),
+                    json!({ sender.to_string(): { "nonce": 2 } }),
+                ),
+            ),
+            // the prestate of another sender's transaction shows the first
+            // sender's nonce, which it never depends on
+            (
+                2,
+                state(
+                    other,
+                    json!({ other.to_string(): { "nonce": 0 }, sender.to_string(): { "nonce": 2 } }),
+                    json!({ other.to_string(): { "nonce": 1 } }),
+                ),
+            ),
+        ]);
+        assert_eq!(
+            tx_states[&2].read_set.nonce_set,
+            HashSet::from([other]),
+            "untouched nonces are left out of the read set"
+        );
+
+        let edges = build_transaction_dag(&tx_states, &DependencyRules::default());
+
+        assert_eq!(
+            edges,
+            vec![DagEdge {
+                source: 1,
+                target: 0,
+                dep_type: DEP_NONCE * (HAZARD_RAW | HAZARD_WAW | HAZARD_WAR),
+                conflicts: [HAZARD_RAW, HAZARD_WAW, HAZARD_WAR]
+                    .into_iter()
+                    .map(|hazard| Conflict {
+                        key: StateKey::Nonce(sender),
+                        hazard,
+                        delegation: false,
+                    })
+                    .collect(),
+            }]
+        );
+    }
+
+    #[test]
+    fn balance_exclusions_drop_fee_recipient_dependencies() {
+        let fee_recipient = address!("0x0000000000000000000000000000000000000fee");
+        let token = address!("0x00000000000000000000000000000000000000bb");
+        let paying = |slot: u8| TransactionStateSet {
+            read_set: state_set(&[fee_recipient], &[]),
+            write_set: state_set(&[fee_recipient], &[(token, B256::with_last_byte(slot))]),
+            authorities: HashSet::new(),
+        };
+        let tx_states = BTreeMap::from([(0, paying(0)), (1, paying(1))]);
+
+        let naive = build_transaction_dag(&tx_states, &DependencyRules::default());
+        assert_eq!(naive.len(), 1);
+        assert_eq!(
+            naive[0].dep_type,
+            DEP_BALANCE * (HAZARD_RAW | HAZARD_WAW | HAZARD_WAR)
+        );
+
+        let mut config = AnalysisConfig::default();
+        config.lazy_fee.exclude_fee_recipient = true;
+        config.lazy_fee.exclude_burn_address = true;
+        let rules = BlockAnalyzer::new(config).dependency_rules(Some(fee_recipient));
+        assert_eq!(
+            rules.balance_exclusions,
+            HashSet::from([fee_recipient, Address::ZERO])
+        );
+        assert_eq!(build_transaction_dag(&tx_states, &rules), vec![]);
+    }
+
+    #[test]
+    fn eip7702_authorities_conflict_on_their_delegation() {
+        let sender = address!("0x00000000000000000000000000000000000000a0");
+        let authority = address!("0x00000000000000000000000000000000000000dd");
+        // an authorization with a stale nonce, skipped by the EVM
+        let invalid = address!("0x00000000000000000000000000000000000000de");
+        // tx `tx_index` calls `callee` and runs its code
+        let call = |tx_index: u8, callee: Address| {
+            let caller = Address::with_last_byte(0xa0 + tx_index);
+            traces_to_state_set(
+                TxHash::ZERO,
+                prestate(json!({
+                    caller.to_string(): { "nonce": 0 },
+                    callee.to_string(): { "code": "0x" },
+                })),
+                prestate_diff(json!({ caller.to_string(): { "nonce": 1 } })),
+                caller,
+                &[],
+            )
+            .unwrap()
+        };
+        let tx_states = BTreeMap::from([
+            // tx 0 delegates the authority to 0xee
+            (
+                0,
+                traces_to_state_set(
+                    TxHash::ZERO,
+                    prestate(json!({
+                        sender.to_string(): { "nonce": 0 },
+                        authority.to_string(): { "nonce": 0, "code": "0x" },
+                        invalid.to_string(): { "nonce": 5, "code": "0x" },
+                    })),
+                    prestate_diff(json!({
+                        sender.to_string(): { "nonce": 1 },
+                        authority.to_string(): {
+                            "nonce": 1,
+                            "code": "0xef010000000000000000000000000000000000000000ee",
+                        },
+                    })),
+                    sender,
+                    &[authority, invalid],
+                )
+                .unwrap(),
+            ),
+            (1, call(1, authority)),
+            (2, call(2, invalid)),
+        ]);
+        assert_eq!(tx_states[&0].authorities, HashSet::from([authority]));
+        assert_eq!(
+            tx_states[&0].write_set.nonce_set,
+            HashSet::from([sender, authority])
+        );
+        assert_eq!(tx_states[&0].write_set.code_set, HashSet::from([authority]));
+
+        let edges = build_transaction_dag(&tx_states, &DependencyRules::default());
+
+        // tx 2 calls the invalid authority without depending on tx 0
+        assert_eq!(
+            edges,
+            vec![DagEdge {
+                source: 1,
+                target: 0,
+                dep_type: DEP_CODE * HAZARD_RAW,
+                conflicts: vec![Conflict {
+                    key: StateKey::Code(authority),
+                    hazard: HAZARD_RAW,
+                    delegation: true,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn pseudo_transactions_join_the_dag() {
+        let mut recorded = recorded_block_json();
+        let block = &mut recorded["block"];
+        // a Prague block whose only withdrawal pays the sender of tx 1
+        block["parentBeaconBlockRoot"] = json!(B256::ZERO);
+        block["requestsHash"] = json!(B256::ZERO);
+        block["withdrawals"] = json!([{
+            "index": "0x0",
+            "validatorIndex": "0x0",
+            "address": "0x00000000000000000000000000000000000000a1",
+            "amount": "0x1",
+        }]);
+        let recorded: RecordedBlock = serde_json::from_value(recorded).unwrap();
+        let transactions = block_transactions(&recorded.block).unwrap();
+        let data = BlockData {
+            tx_states: block_traces_to_states(
+                16,
+                &transactions,
+                recorded.prestate_traces,
+                recorded.diff_traces,
+            )
+            .unwrap(),
+            block: recorded.block,
+            receipts: vec![],
+        };
+
+        let states: BTreeMap<_, _> = pseudo_transaction_states(&data.block, transactions.len())
+            .into_iter()
+            .collect();
+        assert_eq!(states.keys().copied().collect::<Vec<_>>(), vec![-1, 2]);
+        // the block's timestamp is 100
+        assert_eq!(
+            states[&-1].write_set.storage_set,
+            HashSet::from([
+                (BEACON_ROOTS, B256::from(U256::from(100))),
+                (BEACON_ROOTS, B256::from(U256::from(100 + 8191))),
+                (HISTORY_STORAGE, B256::from(U256::from(15))),
+            ])
+        );
+        let withdrawal = address!("0x00000000000000000000000000000000000000a1");
+        assert_eq!(states[&2].read_set.balance_set, HashSet::from([withdrawal]));
+        assert_eq!(
+            states[&2].write_set.balance_set,
+            HashSet::from([withdrawal])
+        );
+
+        let config = AnalysisConfig {
+            pseudo_transactions: true,
+            ..Default::default()
+        };
+        let (_, analysis) = BlockAnalyzer::new(config)
+            .block_analysis(&data, &BTreeMap::new())
+            .unwrap();
+        let edges: Vec<_> = analysis
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.dep_type))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (1, 0, DEP_STORAGE * HAZARD_RAW),
+                (2, 1, DEP_BALANCE * (HAZARD_RAW | HAZARD_WAW | HAZARD_WAR)),
+            ]
+        );
+        assert_eq!(analysis.metrics.tx_count, 2);
+        assert_eq!(analysis.metrics.pseudo_tx_count, 2);
+    }
+
+    #[test]
+    fn empty_withdrawals_have_no_pseudo_transaction() {
+        let mut recorded = recorded_block_json();
+        recorded["block"]["withdrawals"] = json!([]);
+        let recorded: RecordedBlock = serde_json::from_value(recorded).unwrap();
+
+        assert!(withdrawals_state(&recorded.block).is_none());
+        assert!(pseudo_transaction_states(&recorded.block, 2).is_empty());
+    }
+
+    #[test]
+    fn analysis_ranges_split_in_the_backfill_direction() {
+        let mut backfill = BackfillConfig {
+            range_size: 10,
+            ..Default::default()
+        };
+        let bounds = |ranges: Vec<AnalysisRange>| {
+            ranges
+                .iter()
+                .map(|range| (range.start_block, range.end_block, range.next_block))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            bounds(analysis_ranges(1, &backfill, 17, 39)),
+            vec![(17, 26, 26), (27, 36, 36), (37, 39, 39)]
+        );
+        backfill.direction = BackfillDirection::Forward;
+        assert_eq!(
+            bounds(analysis_ranges(1, &backfill, 17, 26)),
+            vec![(17, 26, 17)]
+        );
+        assert!(analysis_ranges(1, &backfill, 40, 39).is_empty());
+    }
+
+    #[test]
+    fn uncovered_spans_fill_the_gaps_between_ranges() {
+        let ranges: Vec<AnalysisRange> = [(20, 29), (5, 9), (25, 34), (50, 59)]
+            .into_iter()
+            .flat_map(|(from_block, to_block)| {
+                analysis_ranges(1, &BackfillConfig::default(), from_block, to_block)
+            })
+            .collect();
+
+        assert_eq!(
+            uncovered_spans(0, 60, &ranges),
+            vec![(0, 4), (10, 19), (35, 49), (60, 60)]
+        );
+        assert_eq!(uncovered_spans(12, 45, &ranges), vec![(12, 19), (35, 45)]);
+        assert_eq!(uncovered_spans(21, 33, &ranges), vec![]);
+        assert_eq!(uncovered_spans(0, 3, &ranges), vec![(0, 3)]);
+        assert_eq!(uncovered_spans(0, 10, &[]), vec![(0, 10)]);
+        assert_eq!(uncovered_spans(10, 9, &ranges), vec![]);
+    }
+
+    /// Block 16 replayed from the recorded RPC responses.
+    fn replay_config() -> Config {
+        test_config(&format!(
+            r#"
+            [rpc_cache]
+            mode = "replay"
+            dir = "{}/testdata/rpc_cache"
+            "#,
+            env!("CARGO_MANIFEST_DIR")
+        ))
+    }
+
+    #[tokio::test]
+    async fn analyzes_block_replayed_from_rpc_cache() {
+        // block 16 is served from the cache, neither the node nor the
+        // database are reached
+        let analyzer = ParallelAnalyzer::new(Arc::new(unused_db()), replay_config(), 16);
+        let data = analyzer.fetch_block_data(16).await.unwrap();
+        assert_eq!(data.block.header.number, 16);
+        assert_eq!(data.tx_states.len(), 2);
+
+        let (_, analysis) = analyzer
+            .block_analyzer
+            .block_analysis(&data, &data.gas_used().unwrap())
+            .unwrap();
+        let edges: Vec<_> = analysis
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.dep_type))
+            .collect();
+        assert_eq!(edges, vec![(1, 0, DEP_STORAGE * HAZARD_RAW)]);
+        assert_eq!(analysis.metrics.tx_count, 2);
+    }
+
+    #[test]
+    fn stored_state_sets_give_the_traced_dag() {
+        let recorded: RecordedBlock = serde_json::from_value(recorded_block_json()).unwrap();
+        let transactions = block_transactions(&recorded.block).unwrap();
+        let mut tx_states = block_traces_to_states(
+            16,
+            &transactions,
+            recorded.prestate_traces,
+            recorded.diff_traces,
+        )
+        .unwrap();
+        tx_states
+            .get_mut(&1)
+            .unwrap()
+            .authorities
+            .insert(address!("0x00000000000000000000000000000000000000a1"));
+
+        let restored: BTreeMap<_, _> = tx_states
+            .iter()
+            .map(|(tx_index, state)| {
+                // through the JSONB columns of `transaction_state_sets`
+                let stored = serde_json::to_value(state.to_stored(16, *tx_index)).unwrap();
+                let stored: DbTransactionStateSet = serde_json::from_value(stored).unwrap();
+                (stored.tx_index, TransactionStateSet::from(&stored))
+            })
+            .collect();
+
+        assert_eq!(
+            restored.keys().collect::<Vec<_>>(),
+            tx_states.keys().collect::<Vec<_>>()
+        );
+        for (tx_index, state) in &tx_states {
+            let restored = &restored[tx_index];
+            for (set, restored_set) in [
+                (&state.read_set, &restored.read_set),
+                (&state.write_set, &restored.write_set),
+            ] {
+                assert_eq!(
+                    StateSetRecord::from(set),
+                    StateSetRecord::from(restored_set)
+                );
+                assert_eq!(set.storage_set, restored_set.storage_set);
+            }
+            assert_eq!(state.authorities, restored.authorities);
+        }
+        let rules = DependencyRules::default();
+        assert_eq!(
+            build_transaction_dag(&restored, &rules),
+            build_transaction_dag(&tx_states, &rules)
+        );
+    }
+
+    async fn stored_analysis(analyzer: &ParallelAnalyzer) -> (Vec<Value>, Vec<i64>) {
+        let mut dags: Vec<Value> = analyzer
+            .db
+            .get_transaction_dags_by_block_number(16)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|dag| json!([dag.source_tx, dag.target_tx, dag.dep_type, dag.reasons]))
+            .collect();
+        dags.sort_by_key(Value::to_string);
+        let stats = analyzer
+            .db
+            .get_block_parallelism_stats_by_block_number(16)
+            .await
+            .unwrap()
+            .unwrap();
+        (
+            dags,
+            vec![
+                stats.tx_count,
+                stats.edge_count,
+                stats.critical_path_length,
+                stats.total_gas,
+            ],
+        )
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres DATABASE_URL"]
+    async fn reanalysis_from_stored_state_sets_matches_fresh_trace(pool: PgPool) {
+        let analyzer = ParallelAnalyzer::new(Arc::new(DB::new(pool)), replay_config(), 16);
+        let data = analyzer.fetch_block_data(16).await.unwrap();
+        analyzer.store_block_data(&data).await.unwrap();
+        let traced = stored_analysis(&analyzer).await;
+        assert_eq!(traced.0.len(), 1);
+
+        sqlx::query("DELETE FROM transaction_dags")
+            .execute(&analyzer.db.db)
+            .await
+            .unwrap();
+        analyzer.reanalyze_stored_block(16).await.unwrap();
+
+        assert_eq!(stored_analysis(&analyzer).await, traced);
+    }
+
+    /// A node serving block 16 whose `debug_traceBlockByNumber` answers with
+    /// `block_traces` of the recorded traces, counting the
+    /// `debug_traceTransaction` calls.
+    fn fallback_analyzer(
+        block_traces: fn(Vec<Value>) -> Vec<Value>,
+    ) -> (ParallelAnalyzer, Arc<AtomicUsize>) {
+        let recorded = recorded_block_json();
+        let tx_trace_calls = Arc::new(AtomicUsize::new(0));
+        let calls = tx_trace_calls.clone();
+        let node = Node(Arc::new(move |method, params| {
+            let traces = |options: &Value| {
+                let diff_mode = options["tracerConfig"]["diffMode"] == json!(true);
+                let traces = &recorded[if diff_mode {
+                    "diff_traces"
+                } else {
+                    "prestate_traces"
+                }];
+                traces.as_array().unwrap().clone()
+            };
+            match method {
+                "eth_getBlockByNumber" => recorded["block"].clone(),
+                "eth_getBlockReceipts" => json!([]),
+                "debug_traceBlockByNumber" => json!(block_traces(traces(&params[1]))),
+                "debug_traceTransaction" => {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    traces(&params[1])
+                        .into_iter()
+                        .find(|trace| trace["txHash"] == params[0])
+                        .map(|mut trace| trace["result"].take())
+                        .unwrap()
+                }
+                _ => Value::Null,
+            }
+        }));
+        let analyzer = analyzer_with_node(unused_db(), test_config(""), node);
+        (analyzer, tx_trace_calls)
+    }
+
+    /// Block tracing fails with `expected`, and block 16 is traced
+    /// transaction by transaction instead.
+    async fn assert_falls_back_to_transaction_tracing(
+        block_traces: fn(Vec<Value>) -> Vec<Value>,
+        expected: AnalyzerError,
+    ) {
+        let (analyzer, tx_trace_calls) = fallback_analyzer(block_traces);
+        let block = analyzer.get_block(16).await.unwrap();
+        let error = analyzer
+            .trace_block_state(16, &block_transactions(&block).unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(analyzer_error(&error), Some(&expected));
+
+        let data = analyzer.fetch_block_data(16).await.unwrap();
+        // a prestate and a diff trace per transaction
+        assert_eq!(tx_trace_calls.load(Ordering::SeqCst), 4);
+        let (_, analysis) = analyzer
+            .block_analyzer
+            .block_analysis(&data, &BTreeMap::new())
+            .unwrap();
+        let edges: Vec<_> = analysis
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.dep_type))
+            .collect();
+        assert_eq!(edges, vec![(1, 0, DEP_STORAGE * HAZARD_RAW)]);
+    }
+
+    #[tokio::test]
+    async fn short_block_trace_falls_back_to_transaction_tracing() {
+        assert_falls_back_to_transaction_tracing(
+            |mut traces| {
+                traces.pop();
+                traces
+            },
+            AnalyzerError::TraceCountMismatch {
+                block_number: 16,
+                transactions: 2,
+                traces: 1,
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn mismatched_block_trace_falls_back_to_transaction_tracing() {
+        assert_falls_back_to_transaction_tracing(
+            |mut traces| {
+                traces.reverse();
+                traces
+            },
+            AnalyzerError::TraceMismatch {
+                expected: b256!(
+                    "0x0000000000000000000000000000000000000000000000000000000000000100"
+                ),
+                traced: b256!("0x0000000000000000000000000000000000000000000000000000000000000101"),
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn backoff_doubles_up_to_the_maximum() {
+        let config = test_config(
+            r#"
+            [retry]
+            initial_backoff_ms = 100
+            max_backoff_ms = 1000
+            "#,
+        );
+        let analyzer = ParallelAnalyzer::new(Arc::new(unused_db()), config, 0);
+        let backoffs: Vec<_> = [1, 2, 3, 4, 5, 64, u32::MAX]
+            .into_iter()
+            .map(|attempt| analyzer.backoff(attempt).as_millis())
+            .collect();
+        assert_eq!(backoffs, vec![100, 200, 400, 800, 1000, 1000, 1000]);
+    }
+
+    const CANONICAL: u64 = 1;
+    const ORPHANED: u64 = 2;
+
+    fn block_hash(block_number: i64, branch: u64) -> B256 {
+        B256::from(U256::from((branch << 32) | block_number as u64))
+    }
+
+    /// Header-only block `block_number` of `branch`.
+    fn block_json(block_number: i64, branch: u64) -> Value {
+        let mut block = recorded_block_json()["block"].take();
+        block["number"] = json!(format!("{block_number:#x}"));
+        block["hash"] = json!(block_hash(block_number, branch));
+        block["parentHash"] = json!(block_hash(block_number - 1, branch));
+        block["transactions"] = json!([]);
+        block
+    }
+
+    /// An analyzer on a node serving the canonical branch, with the analyzer
+    /// state at `latest_analyzed_block` and `blocks` stored.
+    async fn reorg_analyzer(
+        pool: PgPool,
+        max_reorg_depth: i64,
+        latest_analyzed_block: i64,
+        blocks: &[(i64, u64)],
+    ) -> ParallelAnalyzer {
+        let node = Node(Arc::new(|method, params| match method {
+            "eth_getBlockByNumber" => {
+                let block_number = params[0].as_str().unwrap().trim_start_matches("0x");
+                block_json(i64::from_str_radix(block_number, 16).unwrap(), CANONICAL)
+            }
+            _ => Value::Null,
+        }));
+        let config = test_config(&format!("max_reorg_depth = {max_reorg_depth}"));
+        let analyzer = analyzer_with_node(DB::new(pool), config, node);
+        analyzer
+            .db
+            .insert_parallel_analyzer_state(&ParallelAnalyzerState {
+                latest_block: latest_analyzed_block,
+                chain_id: 1,
+                start_block: 10,
+                latest_analyzed_block,
+                horizon: "latest".to_string(),
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+            .unwrap();
+        for &(block_number, branch) in blocks {
+            analyzer
+                .db
+                .insert_block(&Block {
+                    parent_hash: block_hash(block_number - 1, branch).to_string(),
+                    block_hash: block_hash(block_number, branch).to_string(),
+                    block_number,
+                    gas_used: 0,
+                    gas_limit: 0,
+                    block_timestamp: 0,
+                    base_fee_per_gas: 0,
+                    blob_gas_used: 0,
+                    excess_blob_gas: 0,
+                    dag_version: 0,
+                    fee_recipient: None,
+                    created_at: None,
+                    updated_at: None,
+                })
+                .await
+                .unwrap();
+        }
+        analyzer
+    }
+
+    fn canonical_block_data(block_number: i64) -> BlockData {
+        BlockData {
+            block: serde_json::from_value(block_json(block_number, CANONICAL)).unwrap(),
+            receipts: vec![],
+            tx_states: BTreeMap::new(),
+        }
+    }
+
+    async fn stored_blocks(analyzer: &ParallelAnalyzer) -> Vec<(i64, String)> {
+        sqlx::query_as("SELECT block_number, block_hash FROM blocks ORDER BY block_number")
+            .fetch_all(&analyzer.db.db)
+            .await
+            .unwrap()
+    }
+
+    async fn latest_analyzed_block(analyzer: &ParallelAnalyzer) -> i64 {
+        analyzer
+            .db
+            .get_parallel_analyzer_state_by_chainid(1)
+            .await
+            .unwrap()
+            .unwrap()
+            .latest_analyzed_block
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres DATABASE_URL"]
+    async fn reorg_below_mismatched_parent_rolls_back_to_fork_block(pool: PgPool) {
+        let analyzer = reorg_analyzer(
+            pool,
+            64,
+            12,
+            &[(10, CANONICAL), (11, ORPHANED), (12, ORPHANED)],
+        )
+        .await;
+
+        let fork_block = analyzer
+            .detect_reorg(&canonical_block_data(13))
+            .await
+            .unwrap();
+
+        assert_eq!(fork_block, Some(10));
+        assert_eq!(
+            stored_blocks(&analyzer).await,
+            vec![(10, block_hash(10, CANONICAL).to_string())]
+        );
+        let reorgs = analyzer.db.get_reorgs_by_chainid(1, 10).await.unwrap();
+        assert_eq!(reorgs.len(), 1);
+        assert_eq!((reorgs[0].fork_block, reorgs[0].orphaned_blocks), (10, 2));
+        assert_eq!(
+            reorgs[0].old_block_hash,
+            block_hash(11, ORPHANED).to_string()
+        );
+        assert_eq!(
+            reorgs[0].new_block_hash,
+            block_hash(11, CANONICAL).to_string()
+        );
+        assert_eq!(latest_analyzed_block(&analyzer).await, 10);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres DATABASE_URL"]
+    async fn reorg_of_stored_block_at_same_height_is_rolled_back(pool: PgPool) {
+        let analyzer = reorg_analyzer(
+            pool,
+            64,
+            13,
+            &[(11, CANONICAL), (12, CANONICAL), (13, ORPHANED)],
+        )
+        .await;
+
+        let fork_block = analyzer
+            .detect_reorg(&canonical_block_data(13))
+            .await
+            .unwrap();
+
+        assert_eq!(fork_block, Some(12));
+        assert_eq!(
+            stored_blocks(&analyzer).await,
+            vec![
+                (11, block_hash(11, CANONICAL).to_string()),
+                (12, block_hash(12, CANONICAL).to_string()),
+            ]
+        );
+        assert_eq!(latest_analyzed_block(&analyzer).await, 12);
+        // the canonical chain is left alone
+        assert_eq!(
+            analyzer
+                .detect_reorg(&canonical_block_data(13))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres DATABASE_URL"]
+    async fn reorg_walks_back_through_unstored_blocks(pool: PgPool) {
+        // block 11 failed and was skipped, block 10 below it is orphaned too
+        let analyzer = reorg_analyzer(
+            pool,
+            64,
+            13,
+            &[
+                (9, CANONICAL),
+                (10, ORPHANED),
+                (12, ORPHANED),
+                (13, ORPHANED),
+            ],
+        )
+        .await;
+
+        let fork_block = analyzer
+            .detect_reorg(&canonical_block_data(14))
+            .await
+            .unwrap();
+
+        assert_eq!(fork_block, Some(9));
+        assert_eq!(
+            stored_blocks(&analyzer).await,
+            vec![(9, block_hash(9, CANONICAL).to_string())]
+        );
+        let reorgs = analyzer.db.get_reorgs_by_chainid(1, 10).await.unwrap();
+        assert_eq!(reorgs[0].orphaned_blocks, 3);
+        assert_eq!(latest_analyzed_block(&analyzer).await, 9);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres DATABASE_URL"]
+    async fn reorg_deeper_than_max_depth_changes_nothing(pool: PgPool) {
+        let blocks = [
+            (9, CANONICAL),
+            (10, ORPHANED),
+            (11, ORPHANED),
+            (12, ORPHANED),
+            (13, ORPHANED),
+        ];
+        let analyzer = reorg_analyzer(pool, 2, 13, &blocks).await;
+
+        let e = analyzer
+            .detect_reorg(&canonical_block_data(14))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            analyzer_error(&e),
+            Some(AnalyzerError::ReorgTooDeep {
+                block_number: 13,
+                max_depth: 2
+            })
+        ));
+        assert_eq!(stored_blocks(&analyzer).await.len(), blocks.len());
+        assert!(
+            analyzer
+                .db
+                .get_reorgs_by_chainid(1, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(latest_analyzed_block(&analyzer).await, 13);
+    }
+}
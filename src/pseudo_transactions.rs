@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use alloy::{
+    network::AnyRpcBlock as AlloyBlock,
+    primitives::{B256, U256},
+};
+
+use crate::{
+    chain_profile::{BEACON_ROOTS, HISTORY_STORAGE},
+    parallel_analyzer::{StateSet, TransactionStateSet},
+};
+
+/// Index of the pre-block system calls, before every transaction.
+pub const SYSTEM_CALLS_TX_INDEX: i64 = -1;
+
+/// Ring buffer length of the EIP-4788 and EIP-2935 contracts.
+const HISTORY_BUFFER_LENGTH: u64 = 8191;
+
+/// Index of the post-block withdrawal phase, after every transaction of a
+/// block with `tx_count` transactions.
+pub fn withdrawals_tx_index(tx_count: usize) -> i64 {
+    tx_count as i64
+}
+
+/// Name of the pseudo-transaction at `index`, if there is one.
+pub fn pseudo_transaction_name(index: i64, tx_count: usize) -> Option<&'static str> {
+    if index == SYSTEM_CALLS_TX_INDEX {
+        Some("system_calls")
+    } else if index == withdrawals_tx_index(tx_count) {
+        Some("withdrawals")
+    } else {
+        None
+    }
+}
+
+fn slot(index: u64) -> B256 {
+    B256::from(U256::from(index))
+}
+
+/// Storage written by the EIP-4788 beacon root and EIP-2935 block hash
+/// system calls, `None` before Cancun.
+pub fn system_calls_state(block: &AlloyBlock) -> Option<TransactionStateSet> {
+    let header = &block.header;
+    header.parent_beacon_block_root?;
+    let mut state = TransactionStateSet::default();
+    let timestamp_index = header.timestamp % HISTORY_BUFFER_LENGTH;
+    state.write_set.storage_set.extend([
+        (BEACON_ROOTS, slot(timestamp_index)),
+        (BEACON_ROOTS, slot(timestamp_index + HISTORY_BUFFER_LENGTH)),
+    ]);
+    // the history storage contract is called from Prague on
+    if header.requests_hash.is_some() && header.number > 0 {
+        state.write_set.storage_set.insert((
+            HISTORY_STORAGE,
+            slot((header.number - 1) % HISTORY_BUFFER_LENGTH),
+        ));
+    }
+    Some(state)
+}
+
+/// Balances credited by the block's withdrawals, `None` before Shanghai and
+/// for blocks without withdrawals.
+pub fn withdrawals_state(block: &AlloyBlock) -> Option<TransactionStateSet> {
+    let withdrawals = block
+        .withdrawals
+        .as_ref()
+        .filter(|withdrawals| !withdrawals.is_empty())?;
+    let balance_set: HashSet<_> = withdrawals
+        .iter()
+        .map(|withdrawal| withdrawal.address)
+        .collect();
+    Some(TransactionStateSet {
+        read_set: StateSet {
+            balance_set: balance_set.clone(),
+            ..Default::default()
+        },
+        write_set: StateSet {
+            balance_set,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+/// Read/write sets of the block's pseudo-transactions, keyed by their index.
+pub fn pseudo_transaction_states(
+    block: &AlloyBlock,
+    tx_count: usize,
+) -> Vec<(i64, TransactionStateSet)> {
+    let system_calls = system_calls_state(block).map(|state| (SYSTEM_CALLS_TX_INDEX, state));
+    let withdrawals = withdrawals_state(block).map(|state| (withdrawals_tx_index(tx_count), state));
+    system_calls.into_iter().chain(withdrawals).collect()
+}
@@ -0,0 +1,378 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use alloy::{
+    primitives::{B256, keccak256},
+    rpc::json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    transports::{TransportError, TransportErrorKind, TransportFut},
+};
+use serde_derive::Deserialize;
+use serde_json::value::RawValue;
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// How `execution_api` responses go through `rpc_cache.dir`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcCacheMode {
+    /// every request goes to the node
+    #[default]
+    Off,
+    /// cacheable responses from the node are saved
+    Record,
+    /// cacheable requests are served from the cache only, the others fail,
+    /// so the head cannot be followed
+    Replay,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcCacheConfig {
+    #[serde(default)]
+    pub mode: RpcCacheMode,
+    #[serde(default = "default_cache_dir")]
+    pub dir: PathBuf,
+}
+
+impl Default for RpcCacheConfig {
+    fn default() -> Self {
+        Self {
+            mode: RpcCacheMode::default(),
+            dir: default_cache_dir(),
+        }
+    }
+}
+
+fn default_cache_dir() -> PathBuf {
+    PathBuf::from("rpc-cache")
+}
+
+/// Methods whose response is fixed once the requested block is mined.
+const CACHED_METHODS: [&str; 5] = [
+    "eth_getBlockByNumber",
+    "eth_getBlockReceipts",
+    "eth_getTransactionReceipt",
+    "debug_traceBlockByNumber",
+    "debug_traceTransaction",
+];
+
+/// Block tags that move with the chain.
+const MOVING_TAGS: [&str; 5] = [
+    "\"latest\"",
+    "\"pending\"",
+    "\"safe\"",
+    "\"finalized\"",
+    "\"earliest\"",
+];
+
+/// Cache key of a request, `None` if its response can still change.
+fn cache_key(request: &SerializedRequest) -> Option<B256> {
+    if !CACHED_METHODS.contains(&request.method()) {
+        return None;
+    }
+    let params = request.params().map_or("", RawValue::get);
+    if MOVING_TAGS.iter().any(|tag| params.contains(tag)) {
+        return None;
+    }
+    Some(keccak256(format!("{}{}", request.method(), params)))
+}
+
+/// Records responses of the node into a directory keyed by the keccak256 of
+/// each request's method and params, or replays them from it.
+#[derive(Debug, Clone)]
+pub struct RpcCacheLayer {
+    mode: RpcCacheMode,
+    dir: Arc<PathBuf>,
+}
+
+impl RpcCacheLayer {
+    pub fn new(config: &RpcCacheConfig) -> Self {
+        Self {
+            mode: config.mode,
+            dir: Arc::new(config.dir.clone()),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcCacheLayer {
+    type Service = RpcCacheService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcCacheService {
+            inner,
+            mode: self.mode,
+            dir: self.dir.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcCacheService<S> {
+    inner: S,
+    mode: RpcCacheMode,
+    dir: Arc<PathBuf>,
+}
+
+impl<S> RpcCacheService<S> {
+    /// `<dir>/<first byte>/<key>.json`, spreading entries over 256
+    /// subdirectories.
+    fn path(&self, key: B256) -> PathBuf {
+        self.dir
+            .join(format!("{:02x}", key[0]))
+            .join(format!("{key:x}.json"))
+    }
+}
+
+async fn read_entry(
+    path: PathBuf,
+    request: SerializedRequest,
+) -> Result<ResponsePacket, TransportError> {
+    let content = tokio::fs::read_to_string(&path).await.map_err(|_| {
+        TransportErrorKind::custom_str(&format!(
+            "{} {} not recorded in the rpc cache",
+            request.method(),
+            request.params().map_or("", RawValue::get)
+        ))
+    })?;
+    let result = RawValue::from_string(content).map_err(TransportErrorKind::custom)?;
+    Ok(ResponsePacket::Single(Response {
+        id: request.id().clone(),
+        payload: ResponsePayload::Success(result),
+    }))
+}
+
+/// Suffix of the next temporary file, unique within the process.
+static TMP_SUFFIX: AtomicU64 = AtomicU64::new(0);
+
+/// Writes through a temporary file so concurrent readers never see a partial
+/// entry, and concurrent writers of the same entry never share one.
+async fn write_entry(path: PathBuf, result: &RawValue) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = path.with_extension(format!(
+        "tmp-{}-{}",
+        std::process::id(),
+        TMP_SUFFIX.fetch_add(1, Ordering::Relaxed)
+    ));
+    tokio::fs::write(&tmp, result.get()).await?;
+    tokio::fs::rename(&tmp, &path).await
+}
+
+impl<S> Service<RequestPacket> for RpcCacheService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        let request = match (&packet, self.mode) {
+            (_, RpcCacheMode::Off) => return self.inner.call(packet),
+            (RequestPacket::Single(request), _) => request.clone(),
+            (RequestPacket::Batch(_), RpcCacheMode::Record) => return self.inner.call(packet),
+            (RequestPacket::Batch(_), RpcCacheMode::Replay) => {
+                return Box::pin(async {
+                    Err(TransportErrorKind::custom_str(
+                        "batch requests are not replayed from the rpc cache",
+                    ))
+                });
+            }
+        };
+        let key = cache_key(&request);
+        match (self.mode, key) {
+            (RpcCacheMode::Replay, Some(key)) => Box::pin(read_entry(self.path(key), request)),
+            (RpcCacheMode::Replay, None) => Box::pin(async move {
+                Err(TransportErrorKind::custom_str(&format!(
+                    "{} is not replayed from the rpc cache",
+                    request.method()
+                )))
+            }),
+            (RpcCacheMode::Record, Some(key)) => {
+                let path = self.path(key);
+                let response = self.inner.call(packet);
+                Box::pin(async move {
+                    let response = response.await?;
+                    if let ResponsePacket::Single(Response {
+                        payload: ResponsePayload::Success(result),
+                        ..
+                    }) = &response
+                    {
+                        // a block the node does not have yet
+                        if result.get() != "null" {
+                            if let Err(e) = write_entry(path, result).await {
+                                warn!("Could not record {}: {:?}", request.method(), e);
+                            }
+                        }
+                    }
+                    Ok(response)
+                })
+            }
+            _ => self.inner.call(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use alloy::rpc::json_rpc::{Id, Request};
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn request(id: u64, method: &'static str, params: Value) -> SerializedRequest {
+        Request::new(method, Id::Number(id), params)
+            .serialize()
+            .unwrap()
+    }
+
+    /// Answers every request with its method and params, counting the calls.
+    /// A fresh directory under the system temp dir, removed on drop even when
+    /// the test fails.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(prefix: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!("{prefix}-{}-{nanos}", std::process::id())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Node {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Service<RequestPacket> for Node {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, packet: RequestPacket) -> Self::Future {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let RequestPacket::Single(request) = packet else {
+                unreachable!("the tests send single requests")
+            };
+            let result = json!({
+                "method": request.method(),
+                "params": request.params().map(|params| params.get()),
+            });
+            Box::pin(async move {
+                Ok(ResponsePacket::Single(Response {
+                    id: request.id().clone(),
+                    payload: ResponsePayload::Success(
+                        RawValue::from_string(result.to_string()).unwrap(),
+                    ),
+                }))
+            })
+        }
+    }
+
+    fn result(response: ResponsePacket) -> (Id, String) {
+        let ResponsePacket::Single(Response {
+            id,
+            payload: ResponsePayload::Success(result),
+        }) = response
+        else {
+            panic!("not a single successful response: {response:?}")
+        };
+        (id, result.get().to_string())
+    }
+
+    #[test]
+    fn cache_key_skips_moving_blocks_and_other_methods() {
+        let block = |id, params| cache_key(&request(id, "eth_getBlockByNumber", params));
+        let key = block(1, json!(["0x10", true])).unwrap();
+        // the request id is not part of the key
+        assert_eq!(block(2, json!(["0x10", true])), Some(key));
+        assert_ne!(block(1, json!(["0x11", true])), Some(key));
+        assert_ne!(block(1, json!(["0x10", false])), Some(key));
+        assert_ne!(
+            cache_key(&request(1, "eth_getBlockReceipts", json!(["0x10", true]))),
+            Some(key)
+        );
+        for tag in ["latest", "pending", "safe", "finalized", "earliest"] {
+            assert_eq!(block(1, json!([tag, true])), None);
+        }
+        assert_eq!(cache_key(&request(1, "eth_blockNumber", json!([]))), None);
+        assert_eq!(
+            cache_key(&request(1, "eth_getBalance", json!(["0x00", "0x10"]))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_responses() {
+        let dir = TempDir::new("rpc-cache-test");
+        let config = |mode| RpcCacheConfig {
+            mode,
+            dir: dir.0.clone(),
+        };
+        let node = Node::default();
+        let block = || request(1, "eth_getBlockByNumber", json!(["0x10", true]));
+
+        let mut recorder = RpcCacheLayer::new(&config(RpcCacheMode::Record)).layer(node.clone());
+        let recorded = result(recorder.call(block().into()).await.unwrap());
+        recorder
+            .call(request(2, "eth_blockNumber", json!([])).into())
+            .await
+            .unwrap();
+        assert_eq!(node.calls.load(Ordering::SeqCst), 2);
+
+        let mut replayer = RpcCacheLayer::new(&config(RpcCacheMode::Replay)).layer(node.clone());
+        let replayed = result(
+            replayer
+                .call(request(3, "eth_getBlockByNumber", json!(["0x10", true])).into())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(replayed, (Id::Number(3), recorded.1));
+        // neither the uncacheable head nor an unrecorded block reach the node
+        assert!(
+            replayer
+                .call(request(4, "eth_blockNumber", json!([])).into())
+                .await
+                .is_err()
+        );
+        assert!(
+            replayer
+                .call(request(5, "eth_getBlockByNumber", json!(["0x11", true])).into())
+                .await
+                .is_err()
+        );
+        assert_eq!(node.calls.load(Ordering::SeqCst), 2);
+    }
+}
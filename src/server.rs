@@ -1,10 +1,13 @@
-use std::sync::Arc;
+use std::sync::{Arc, atomic::AtomicBool};
 
 use alloy::{
-    network::Ethereum,
+    network::AnyNetwork,
     providers::{Provider, RootProvider},
 };
-use axum::{Router, routing::get};
+use axum::{
+    Router,
+    routing::{get, post},
+};
 use eyre::Result;
 use tokio::net::TcpListener;
 use tower_http::{catch_panic::CatchPanicLayer, cors::CorsLayer};
@@ -13,8 +16,15 @@ use crate::{
     config::Config,
     db::DB,
     handlers::{
-        common::{handle_404, handle_panic, health_check},
-        transaction_dag::{handle_parallel_analyzer_state, handle_transaction_dag},
+        analysis_ranges::handle_analysis_ranges,
+        block_parallelism_stats::handle_block_parallelism_stats,
+        common::{handle_404, handle_metrics, handle_panic, health_check},
+        failed_blocks::{handle_failed_blocks, handle_retry_failed_blocks},
+        schedule_sim::handle_speedup_curve,
+        transaction_dag::{
+            handle_parallel_analyzer_state, handle_transaction_dag, handle_transaction_dag_edge,
+            handle_transaction_state_set,
+        },
     },
 };
 
@@ -22,8 +32,10 @@ use crate::{
 pub struct ServerState {
     pub db: Arc<DB>,
     pub config: Arc<Config>,
-    pub execution_api_client: Arc<RootProvider<Ethereum>>,
+    pub execution_api_client: Arc<RootProvider<AnyNetwork>>,
     pub chain_id: i64,
+    /// set while failed blocks are being retried
+    pub retry_in_flight: Arc<AtomicBool>,
 }
 
 impl ServerState {
@@ -34,17 +46,38 @@ impl ServerState {
             config: Arc::new(config.clone()),
             execution_api_client: Arc::new(provider),
             chain_id: config.chain_id,
+            retry_in_flight: Arc::new(AtomicBool::new(false)),
         })
     }
 
     fn config_router(&self) -> Router {
         Router::new()
             .route("/health", get(health_check))
+            .route("/metrics", get(handle_metrics))
             .route("/data/evm/transaction-dag", get(handle_transaction_dag))
+            .route(
+                "/data/evm/transaction-dag/edge",
+                get(handle_transaction_dag_edge),
+            )
+            .route(
+                "/data/evm/transaction-state-set",
+                get(handle_transaction_state_set),
+            )
             .route(
                 "/data/evm/parallel-analyzer-state",
                 get(handle_parallel_analyzer_state),
             )
+            .route(
+                "/data/evm/block-parallelism-stats",
+                get(handle_block_parallelism_stats),
+            )
+            .route("/data/evm/speedup-curve", get(handle_speedup_curve))
+            .route("/data/evm/failed-blocks", get(handle_failed_blocks))
+            .route("/data/evm/analysis-ranges", get(handle_analysis_ranges))
+            .route(
+                "/data/evm/failed-blocks/retry",
+                post(handle_retry_failed_blocks),
+            )
             .fallback(get(handle_404))
             .layer(CatchPanicLayer::custom(handle_panic))
             .layer(CorsLayer::permissive())